@@ -0,0 +1,495 @@
+use std::io::{self, Write};
+
+use rand::Rng;
+
+use game::{GameMove, GameState, PlayerView, Rules};
+use logic;
+use rng::GameRng;
+use strategy::{BotKind, Strategy};
+
+// A game that has not ended after this many moves is stopped, and the players
+// still in it share the remaining places
+pub const MAX_MOVES: usize = 10_000;
+
+// Ratings start here and move by at most K_FACTOR per game
+pub const START_ELO: f64 = 1500.0;
+const K_FACTOR: f64 = 32.0;
+
+// A strategy taking part in a tournament
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub kind: BotKind,
+}
+
+// What a tournament plays
+#[derive(Clone, Debug)]
+pub struct ArenaConfig {
+    pub games: usize,
+    // game i is dealt from seed + i
+    pub seed: u64,
+    // (players, cards per hand) of the tables, played in turn
+    pub tables: Vec<(u8, u8)>,
+    pub rules: Rules,
+}
+
+impl ArenaConfig {
+    // checks that every table can be dealt under the rules
+    pub fn check(&self) -> Result<(), String> {
+        if self.tables.is_empty() {
+            return Err("the arena needs a table".to_owned());
+        }
+        for &(players, cards) in &self.tables {
+            self.rules
+                .check_table(players, cards)
+                .map_err(|error| format!("table {}x{}: {}", players, cards, error))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for ArenaConfig {
+    fn default() -> ArenaConfig {
+        ArenaConfig {
+            games: 1000,
+            seed: 0,
+            tables: vec![(2, 3)],
+            rules: Rules::default(),
+        }
+    }
+}
+
+// How one game went
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameResult {
+    pub game: usize,
+    pub seed: u64,
+    pub cards: u8,
+    // index into the entries of the strategy in each seat
+    pub seats: Vec<usize>,
+    // finishing place of each seat, 1 for the winner. Players who were still
+    // in a stopped game share the places left.
+    pub places: Vec<f64>,
+    pub moves: usize,
+}
+
+// Running totals for one entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub games: usize,
+    pub wins: usize,
+    pub place_sum: f64,
+    pub place_sq_sum: f64,
+    pub elo: f64,
+    // pairings with seats of other entries, and the sum of their scores:
+    // 1 ahead, 0.5 level, 0 behind
+    pub pairs: usize,
+    pub score_sum: f64,
+}
+
+impl Default for Standing {
+    fn default() -> Standing {
+        Standing {
+            games: 0,
+            wins: 0,
+            place_sum: 0.0,
+            place_sq_sum: 0.0,
+            elo: START_ELO,
+            pairs: 0,
+            score_sum: 0.0,
+        }
+    }
+}
+
+impl Standing {
+    // returns the win rate with its 95% Wilson interval
+    pub fn win_rate(&self) -> logic::Estimate {
+        logic::wilson(self.wins, self.games, 1.96)
+    }
+
+    // returns the average place with the half width of its 95% interval
+    pub fn average_place(&self) -> (f64, f64) {
+        if self.games == 0 {
+            return (0.0, 0.0);
+        }
+        let n = self.games as f64;
+        let mean = self.place_sum / n;
+        let variance = (self.place_sq_sum / n - mean * mean).max(0.0);
+        (mean, 1.96 * (variance / n).sqrt())
+    }
+
+    // Returns the half width of the 95% interval of the Elo rating: the
+    // Wilson interval of the average score against other entries, turned
+    // into Elo differences. Scores are kept a little away from 0 and 1, where
+    // the difference would be infinite.
+    pub fn elo_interval(&self) -> f64 {
+        if self.pairs == 0 {
+            return f64::INFINITY;
+        }
+        let score = logic::wilson(self.score_sum.round() as usize, self.pairs, 1.96);
+        let edge = 1.0 / (self.pairs + 1) as f64;
+        let elo = |s: f64| {
+            let s = s.max(edge).min(1.0 - edge);
+            400.0 * (s / (1.0 - s)).log10()
+        };
+        (elo(score.high) - elo(score.low)) / 2.0
+    }
+}
+
+// Plays seeded games between entries, rotating who sits where
+pub struct Arena {
+    pub config: ArenaConfig,
+    pub entries: Vec<Entry>,
+    pub standings: Vec<Standing>,
+    // players of every entry that are free for the next game. Each is made
+    // once and readied with Strategy::new_game before every game it plays.
+    bots: Vec<Vec<Box<dyn Strategy>>>,
+}
+
+impl Arena {
+    pub fn new(config: ArenaConfig, entries: Vec<Entry>) -> Arena {
+        let standings = vec![Standing::default(); entries.len()];
+        let bots = entries.iter().map(|_| vec![]).collect();
+        Arena {
+            config,
+            entries,
+            standings,
+            bots,
+        }
+    }
+
+    // Returns the entry in each seat of the game. The tables are played in
+    // turn, and each table goes through every ordering of different entries
+    // in its seats: the entries of an ordering are offsets from a first
+    // entry, which moves on every time the table is played, and the offsets
+    // change once every entry has been first. So every run of as many games
+    // at a table as there are entries fills each seat with each entry once,
+    // and in the long run every group of entries meets in every order. A
+    // table with more seats than entries repeats the ordering of all of them.
+    pub fn seating(&self, game: usize, players: usize) -> Vec<usize> {
+        let n = self.entries.len();
+        let round = game / self.config.tables.len();
+        let (first, mut pattern) = (round % n, round / n);
+        let mut free: Vec<usize> = (1..n).collect();
+        let mut order = vec![first];
+        while order.len() < players.min(n) {
+            let offset = free.remove(pattern % free.len());
+            pattern /= free.len() + 1;
+            order.push((first + offset) % n);
+        }
+        (0..players).map(|seat| order[seat % order.len()]).collect()
+    }
+
+    // Plays the next game, updates the standings and returns the result.
+    // Fails if the table of the game cannot be dealt.
+    pub fn play(&mut self, game: usize) -> Result<GameResult, String> {
+        let (players, cards) = self.config.tables[game % self.config.tables.len()];
+        let seed = self.config.seed.wrapping_add(game as u64);
+        let seats = self.seating(game, players as usize);
+        let mut rng = GameRng::from_seed(seed);
+        let mut bots = vec![];
+        for &entry in &seats {
+            let seed = rng.next_u64();
+            let bot = match self.bots[entry].pop() {
+                Some(mut bot) => {
+                    bot.new_game(seed);
+                    bot
+                }
+                None => self.entries[entry].kind.build(seed),
+            };
+            bots.push(bot);
+        }
+        let played = play_bots(&mut bots, cards, self.config.rules, seed, &mut |_, _| {});
+        for (&entry, bot) in seats.iter().zip(bots) {
+            self.bots[entry].push(bot);
+        }
+        let (places, moves) = played?;
+        let result = GameResult {
+            game,
+            seed,
+            cards,
+            seats,
+            places,
+            moves,
+        };
+        self.record(&result);
+        Ok(result)
+    }
+
+    // plays every game of the tournament, writing a CSV row per seat of every
+    // game when given a writer
+    pub fn run(&mut self, mut csv: Option<&mut dyn Write>) -> io::Result<()> {
+        self.config.check().map_err(io::Error::other)?;
+        if let Some(ref mut out) = csv {
+            writeln!(out, "game,seed,players,cards,seat,entry,place,moves")?;
+        }
+        for game in 0..self.config.games {
+            let result = self.play(game).map_err(io::Error::other)?;
+            if let Some(ref mut out) = csv {
+                for (seat, &entry) in result.seats.iter().enumerate() {
+                    writeln!(out,
+                             "{},{},{},{},{},{},{},{}",
+                             result.game,
+                             result.seed,
+                             result.seats.len(),
+                             result.cards,
+                             seat,
+                             self.entries[entry].name,
+                             result.places[seat],
+                             result.moves)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Updates the standings with a game. Elo treats the game as a match
+    // between every pair of seats held by different entries.
+    pub fn record(&mut self, result: &GameResult) {
+        let n = result.seats.len();
+        let mut deltas = vec![0.0; self.entries.len()];
+        for i in 0..n {
+            for j in 0..n {
+                let (a, b) = (result.seats[i], result.seats[j]);
+                if a == b {
+                    continue;
+                }
+                let expected = 1.0 /
+                               (1.0 + 10f64.powf((self.standings[b].elo -
+                                                  self.standings[a].elo) /
+                                                 400.0));
+                let score = if result.places[i] < result.places[j] {
+                    1.0
+                } else if result.places[i] == result.places[j] {
+                    0.5
+                } else {
+                    0.0
+                };
+                deltas[a] += K_FACTOR / (n - 1) as f64 * (score - expected);
+                let standing = &mut self.standings[a];
+                standing.pairs += 1;
+                standing.score_sum += score;
+            }
+        }
+        for (seat, &entry) in result.seats.iter().enumerate() {
+            let standing = &mut self.standings[entry];
+            let place = result.places[seat];
+            standing.games += 1;
+            if place == 1.0 {
+                standing.wins += 1;
+            }
+            standing.place_sum += place;
+            standing.place_sq_sum += place * place;
+        }
+        for (standing, delta) in self.standings.iter_mut().zip(deltas) {
+            standing.elo += delta;
+        }
+    }
+
+    // writes one line per entry, best Elo first
+    pub fn report<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        order.sort_by(|&a, &b| {
+            self.standings[b].elo.partial_cmp(&self.standings[a].elo).unwrap()
+        });
+        writeln!(out,
+                 "{:<20} {:>6} {:>19} {:>15} {:>13}",
+                 "entry",
+                 "games",
+                 "win rate (95% CI)",
+                 "avg place",
+                 "elo (95% CI)")?;
+        for i in order {
+            let standing = &self.standings[i];
+            let rate = standing.win_rate();
+            let (place, place_ci) = standing.average_place();
+            writeln!(out,
+                     "{:<20} {:>6} {:>5.1}% ({:>4.1}-{:>4.1}%) {:>8.2} ± {:<4.2} {:>6.0} ± {:<4.0}",
+                     self.entries[i].name,
+                     standing.games,
+                     100.0 * rate.prob,
+                     100.0 * rate.low,
+                     100.0 * rate.high,
+                     place,
+                     place_ci,
+                     standing.elo,
+                     standing.elo_interval())?;
+        }
+        Ok(())
+    }
+}
+
+// Plays one game with a bot of each kind in turn order and returns the place
+// of each seat and the number of moves made. Fails if the hands cannot be
+// dealt under the rules.
+pub fn play_game(kinds: &[BotKind], cards: u8, rules: Rules, seed: u64)
+                 -> Result<(Vec<f64>, usize), String> {
+    play_game_with(kinds, cards, rules, seed, &mut |_, _| {})
+}
+
+// Like play_game, but shows every decision to `observe` before it is made
+pub fn play_game_with(kinds: &[BotKind], cards: u8, rules: Rules, seed: u64,
+                      observe: &mut dyn FnMut(&PlayerView, &GameMove))
+                      -> Result<(Vec<f64>, usize), String> {
+    let mut rng = GameRng::from_seed(seed);
+    let mut bots: Vec<Box<dyn Strategy>> = kinds.iter().map(|k| k.build(rng.next_u64())).collect();
+    play_bots(&mut bots, cards, rules, seed, observe)
+}
+
+// plays one game between the input players, in turn order, like play_game_with
+pub fn play_bots(bots: &mut [Box<dyn Strategy>], cards: u8, rules: Rules, seed: u64,
+                 observe: &mut dyn FnMut(&PlayerView, &GameMove))
+                 -> Result<(Vec<f64>, usize), String> {
+    let n = bots.len();
+    let mut places = vec![0.0; n];
+    rules.check_table(n as u8, cards)?;
+    // check_table made sure the hands fit in the deck
+    let mut state = GameState::seeded(n as u8, cards, rules, seed).unwrap();
+    let mut moves = 0;
+    while state.winner().is_none() && moves < MAX_MOVES {
+        let view = state.view(state.current_turn);
+        let gm = bots[view.player as usize].choose(&view, &state.rules);
+        observe(&view, &gm);
+        state = state.handle_gamemove(gm);
+        moves += 1;
+        // players knocked out by this move take the last free place
+        for (name, place) in places.iter_mut().enumerate() {
+            if *place == 0.0 && !state.players.iter().any(|p| p.name as usize == name) {
+                *place = (state.players.len() + 1) as f64;
+            }
+        }
+    }
+    // the winner, or everyone left in a stopped game, shares the top places
+    let left = state.players.len();
+    for player in &state.players {
+        places[player.name as usize] = (left + 1) as f64 / 2.0;
+    }
+    Ok((places, moves))
+}
+
+#[cfg(test)]
+mod test {
+    use arena::{play_game, Arena, ArenaConfig, Entry};
+    use game::{Penalty, Rules};
+    use strategy::BotKind;
+
+    #[test]
+    fn test_arena_rotates_seats_and_ranks() {
+        let entries = vec![Entry {
+                               name: "hard".to_owned(),
+                               kind: "hard".parse().unwrap(),
+                           },
+                           Entry {
+                               name: "random".to_owned(),
+                               kind: BotKind::Random,
+                           }];
+        let config = ArenaConfig {
+            games: 60,
+            seed: 1,
+            tables: vec![(2, 2), (3, 1)],
+            ..ArenaConfig::default()
+        };
+        let mut arena = Arena::new(config, entries);
+        // games alternate between the tables, and each table rotates its seats
+        assert_eq!(arena.seating(0, 2), vec![0, 1]);
+        assert_eq!(arena.seating(1, 3), vec![0, 1, 0]);
+        assert_eq!(arena.seating(2, 2), vec![1, 0]);
+        assert_eq!(arena.seating(3, 3), vec![1, 0, 1]);
+        for (table, &(players, _)) in arena.config.tables.iter().enumerate() {
+            for seat in 0..players as usize {
+                let mut counts = [0; 2];
+                for game in (table..60).step_by(2) {
+                    counts[arena.seating(game, players as usize)[seat]] += 1;
+                }
+                assert_eq!(counts, [15, 15], "table {} seat {}", table, seat);
+            }
+        }
+        let mut csv = vec![];
+        arena.run(Some(&mut csv)).unwrap();
+        // 30 two player games and 30 three player games
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 1 + 30 * 2 + 30 * 3);
+        let games: usize = arena.standings.iter().map(|s| s.games).sum();
+        assert_eq!(games, 150);
+        let wins: usize = arena.standings.iter().map(|s| s.wins).sum();
+        assert_eq!(wins, 60);
+        assert!(arena.standings[0].elo > arena.standings[1].elo);
+        for standing in &arena.standings {
+            let interval = standing.elo_interval();
+            assert!(interval > 0.0 && interval < 400.0, "{}", interval);
+        }
+        // the same seed plays the same games
+        let mut again = Arena::new(arena.config.clone(), arena.entries.clone());
+        again.run(None).unwrap();
+        assert_eq!(again.standings, arena.standings);
+    }
+
+    #[test]
+    fn test_seating_meets_every_pair_in_both_orders() {
+        for &n in &[3, 4, 5] {
+            let entries = (0..n)
+                .map(|i| {
+                    Entry {
+                        name: i.to_string(),
+                        kind: BotKind::Random,
+                    }
+                })
+                .collect();
+            let arena = Arena::new(ArenaConfig::default(), entries);
+            let mut pairs = vec![];
+            for game in 0..n * (n - 1) {
+                pairs.push(arena.seating(game, 2));
+            }
+            pairs.sort();
+            pairs.dedup();
+            assert_eq!(pairs.len(), n * (n - 1), "{} entries", n);
+            // each run of n games fills every seat with every entry
+            for run in 0..n - 1 {
+                for seat in 0..2 {
+                    let mut firsts: Vec<usize> =
+                        (run * n..(run + 1) * n).map(|game| arena.seating(game, 2)[seat]).collect();
+                    firsts.sort();
+                    assert_eq!(firsts, (0..n).collect::<Vec<_>>());
+                }
+            }
+            // three seats see every ordering of three entries
+            let mut triples: Vec<Vec<usize>> =
+                (0..n * (n - 1) * (n - 2)).map(|game| arena.seating(game, 3)).collect();
+            triples.sort();
+            triples.dedup();
+            assert_eq!(triples.len(), n * (n - 1) * (n - 2));
+        }
+    }
+
+    #[test]
+    fn test_arena_rejects_tables_the_rules_cannot_deal() {
+        let gain = Rules {
+            penalty: Penalty::GainCard,
+            ..Rules::default()
+        };
+        let kinds = vec![BotKind::Random; 11];
+        // 11 hands of 4 cards fit in the deck, but not hands grown to 5
+        assert!(play_game(&kinds, 4, Rules::default(), 1).is_ok());
+        assert!(play_game(&kinds, 4, gain, 1).is_err());
+        // ten hands that may grow to 5 cards use 50 of the 52
+        assert!(play_game(&kinds[..10], 5, gain, 1).is_ok());
+        assert!(play_game(&kinds[..2], 6, gain, 1).is_err());
+        let config = ArenaConfig {
+            games: 10,
+            tables: vec![(2, 3), (11, 4)],
+            rules: gain,
+            ..ArenaConfig::default()
+        };
+        assert!(config.check().unwrap_err().starts_with("table 11x4"));
+        let entries = vec![Entry {
+                               name: "random".to_owned(),
+                               kind: BotKind::Random,
+                           },
+                           Entry {
+                               name: "call".to_owned(),
+                               kind: BotKind::Caller,
+                           }];
+        let mut arena = Arena::new(config, entries);
+        assert!(arena.run(None).is_err());
+        assert!(arena.standings.iter().all(|s| s.games == 0));
+    }
+}
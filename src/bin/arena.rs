@@ -0,0 +1,121 @@
+extern crate bullpoker;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+use bullpoker::arena::{Arena, ArenaConfig, Entry};
+use bullpoker::game::{Penalty, Rules};
+use bullpoker::strategy::BotKind;
+
+const USAGE: &str = "usage: bullpoker-arena [options] <bot> <bot> [bot...]
+
+Plays seeded games between the bots and reports how each did.
+Bots are named as in the game's seat command, e.g. hard, bluff:0.3:0.2.
+
+options:
+  --games <n>           number of games to play (default 1000)
+  --seed <n>            seed of the first game (default 0)
+  --table <players>x<cards>
+                        add a table size, e.g. 3x4; tables are played in turn
+                        (default 2x3)
+  --gain                losing a call gains a card instead of losing one
+  --csv <file>          write one row per seat of every game";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut config = ArenaConfig::default();
+    let mut tables = vec![];
+    let mut entries = vec![];
+    let mut csv_path = None;
+    let mut i = 0;
+    while i < args.len() {
+        let value = args.get(i + 1);
+        match args[i].as_str() {
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--games" => config.games = parse_or_exit(value, "--games"),
+            "--seed" => config.seed = parse_or_exit(value, "--seed"),
+            "--table" => tables.push(parse_table(value)),
+            "--csv" => csv_path = Some(parse_or_exit::<String>(value, "--csv")),
+            "--gain" => {
+                config.rules = Rules {
+                    penalty: Penalty::GainCard,
+                    ..Rules::default()
+                };
+                i += 1;
+                continue;
+            }
+            name => {
+                match name.parse::<BotKind>() {
+                    Ok(kind) => {
+                        entries.push(Entry {
+                            name: name.to_owned(),
+                            kind,
+                        })
+                    }
+                    Err(error) => exit_with(&error),
+                }
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+    if entries.len() < 2 {
+        exit_with("at least two bots are needed");
+    }
+    if !tables.is_empty() {
+        config.tables = tables;
+    }
+    if let Err(error) = config.check() {
+        exit_with(&error);
+    }
+    let mut arena = Arena::new(config, entries);
+    let result = match csv_path {
+        Some(path) => {
+            File::create(&path).and_then(|file| {
+                let mut out = BufWriter::new(file);
+                arena.run(Some(&mut out))?;
+                out.flush()
+            })
+        }
+        None => arena.run(None),
+    };
+    if let Err(error) = result {
+        exit_with(&format!("error: {}", error));
+    }
+    arena.report(&mut io::stdout()).unwrap();
+}
+
+fn parse_or_exit<T: std::str::FromStr>(value: Option<&String>, flag: &str) -> T {
+    match value.and_then(|v| v.parse().ok()) {
+        Some(v) => v,
+        None => exit_with(&format!("{} needs a valid value", flag)),
+    }
+}
+
+fn parse_table(value: Option<&String>) -> (u8, u8) {
+    let parsed = value.and_then(|v| {
+        let vec: Vec<&str> = v.split('x').collect();
+        if vec.len() != 2 {
+            return None;
+        }
+        match (vec[0].parse(), vec[1].parse()) {
+            (Ok(players), Ok(cards)) => Some((players, cards)),
+            _ => None,
+        }
+    });
+    match parsed {
+        Some(table) => table,
+        None => exit_with("--table needs <players>x<cards>, e.g. 3x4"),
+    }
+}
+
+fn exit_with(msg: &str) -> ! {
+    eprintln!("{}\n\n{}", msg, USAGE);
+    process::exit(2);
+}
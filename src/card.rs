@@ -1,5 +1,7 @@
 use std::cmp::Ordering;
 
+use term;
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Suit {
     Clubs,
@@ -41,6 +43,92 @@ impl Suit {
         };
         output
     }
+
+    pub fn to_ascii(self) -> &'static str {
+        match self {
+            Suit::Clubs => "C",
+            Suit::Diamonds => "D",
+            Suit::Hearts => "H",
+            Suit::Spades => "S",
+        }
+    }
+
+    pub fn from_ascii(s: &str) -> Option<Suit> {
+        match s {
+            "C" => Some(Suit::Clubs),
+            "D" => Some(Suit::Diamonds),
+            "H" => Some(Suit::Hearts),
+            "S" => Some(Suit::Spades),
+            _ => None,
+        }
+    }
+
+    pub fn is_red(self) -> bool {
+        match self {
+            Suit::Diamonds | Suit::Hearts => true,
+            Suit::Clubs | Suit::Spades => false,
+        }
+    }
+
+    // position of the suit in Clubs, Diamonds, Hearts, Spades order
+    pub fn to_index(self) -> u8 {
+        match self {
+            Suit::Clubs => 0,
+            Suit::Diamonds => 1,
+            Suit::Hearts => 2,
+            Suit::Spades => 3,
+        }
+    }
+
+    pub fn from_index(index: u8) -> Option<Suit> {
+        match index {
+            0 => Some(Suit::Clubs),
+            1 => Some(Suit::Diamonds),
+            2 => Some(Suit::Hearts),
+            3 => Some(Suit::Spades),
+            _ => None,
+        }
+    }
+}
+
+// Options controlling how cards are drawn to the terminal
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RenderOptions {
+    // color red suits red with ANSI escapes, leaving black suits in the
+    // terminal's own color so that they show on dark backgrounds
+    pub color: bool,
+    // draw each card on a single line ("A♠") instead of a box
+    pub compact: bool,
+    // use S/H/D/C instead of the unicode suit symbols
+    pub ascii: bool,
+    // wrap hands so that no line is wider than this many columns
+    pub width: Option<usize>,
+}
+
+impl RenderOptions {
+    // returns options that wrap at the width of the current terminal
+    pub fn for_terminal() -> RenderOptions {
+        RenderOptions {
+            width: Some(term::width()),
+            ..RenderOptions::default()
+        }
+    }
+
+    pub fn suit_str<'a>(&self, suit: &'a Suit) -> &'a str {
+        if self.ascii {
+            suit.to_ascii()
+        } else {
+            suit.to_string()
+        }
+    }
+
+    fn paint(&self, suit: &Suit, text: &str) -> String {
+        if !self.color {
+            return text.to_owned();
+        }
+        let code = if suit.is_red() { "31" } else { "39" };
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
 }
 
 impl Rank {
@@ -133,37 +221,42 @@ impl Ord for Card {
 
 impl Card {
     pub fn to_single_string(&self, line: u8) -> String {
+        self.render_line(line, &RenderOptions::default())
+    }
+
+    // returns one of the 5 lines of the boxed representation of the card
+    pub fn render_line(&self, line: u8, opts: &RenderOptions) -> String {
+        let suit = opts.suit_str(&self.suit);
         match line {
             0 => "+-----+".to_owned(),
             1 => {
-                let mut output = "|".to_owned();
-                output.push_str(self.rank.to_string());
+                let mut inner = self.rank.to_string().to_owned();
                 if self.rank != Rank::Ten {
-                    output.push(' ');
+                    inner.push(' ');
                 }
-                output.push_str(self.suit.to_string());
-                output.push_str("  |");
-                output
-            }
-            2 => {
-                let mut output = "|  ".to_owned();
-                output.push_str(self.suit.to_string());
-                output.push_str("  |");
-                output
+                inner.push_str(suit);
+                format!("|{}  |", opts.paint(&self.suit, &inner))
             }
+            2 => format!("|  {}  |", opts.paint(&self.suit, suit)),
             3 => {
-                let mut output = "|  ".to_owned();
-                output.push_str(self.suit.to_string());
+                let mut inner = suit.to_owned();
                 if self.rank != Rank::Ten {
-                    output.push(' ');
+                    inner.push(' ');
                 }
-                output.push_str(self.rank.to_string());
-                output.push_str("|");
-                output
+                inner.push_str(self.rank.to_string());
+                format!("|  {}|", opts.paint(&self.suit, &inner))
             }
             _ => "+-----+".to_owned(),
         }
     }
+
+    // returns the one line representation of the card, e.g. "A♠" or "10H"
+    pub fn to_compact_string(&self, opts: &RenderOptions) -> String {
+        let mut output = self.rank.to_string().to_owned();
+        output.push_str(opts.suit_str(&self.suit));
+        opts.paint(&self.suit, &output)
+    }
+
     pub fn to_string(&self) -> String {
         let mut output = "".to_owned();
         for i in 0..4 {
@@ -173,6 +266,35 @@ impl Card {
         output.push_str(&self.to_single_string(4));
         output
     }
+    // returns the card as plain text, e.g. "AS" or "10H"
+    pub fn to_text(self) -> String {
+        format!("{}{}", self.rank.to_string(), self.suit.to_ascii())
+    }
+
+    // parses the output of to_text
+    pub fn from_text(s: &str) -> Option<Card> {
+        if s.len() < 2 || !s.is_char_boundary(s.len() - 1) {
+            return None;
+        }
+        let (rank, suit) = s.split_at(s.len() - 1);
+        Some(Card {
+            rank: Rank::from_str(rank)?,
+            suit: Suit::from_ascii(suit)?,
+        })
+    }
+
+    // returns a number from 0 to 51 that identifies the card
+    pub fn to_index(self) -> u8 {
+        (self.rank.to_u8() - 2) * 4 + self.suit.to_index()
+    }
+
+    pub fn from_index(index: u8) -> Option<Card> {
+        match (Rank::from_u8(index / 4 + 2), Suit::from_index(index % 4)) {
+            (Some(rank), Some(suit)) => Some(Card { suit, rank }),
+            _ => None,
+        }
+    }
+
     pub fn get_all_with_rank(rank: Rank) -> [Card; 4] {
         let mut output = [Card {
             suit: Suit::Clubs,
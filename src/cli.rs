@@ -0,0 +1,298 @@
+use std::path::PathBuf;
+
+use bullpoker::card::RenderOptions;
+use bullpoker::game::{GameState, Penalty, Rules, Seat, UndoPolicy};
+use bullpoker::rng::GameRng;
+use bullpoker::strategy::BotKind;
+use bullpoker::term;
+
+pub const USAGE: &str = "usage: bullpoker [options]
+       bullpoker <table|cfr|train|tune|selfplay|replay> ...
+
+Starts a game of BullPoker in the terminal.
+
+options:
+  -p, --players <n>     number of players (default 2)
+  -c, --cards <n>       cards each player starts with (default 5)
+  -n, --names <a,b,..>  names of the players, in seat order
+  -s, --seed <n>        seed for the deals, to replay a game
+  -v, --variant <lose|gain>
+                        whether losing a call loses a card (default) or gains
+                        one, in which case a player holding --max-hand cards
+                        who loses a call is out
+      --max-hand <n>    largest hand in the gain variant (default 5)
+  -u, --undo <always|last|never>
+                        which moves can be undone: any (default), only the
+                        last one until the next player acts, or none
+  -b, --bot <seat>=<bot>
+                        let a bot play the seat, e.g. --bot 1=hard; may be
+                        given several times. Bots: random, call, minraise,
+                        easy, medium, hard, threshold:<call>:<raise>, bluff,
+                        cfr, ismcts, nn:<file>, ext:<command>
+  -t, --table <file>    look up the probabilities of hints in a table made
+                        with bullpoker table
+      --tui             play full screen, picking bets with the arrow keys
+      --hotseat         hide the hands between turns, for players sharing
+                        one terminal
+      --color           color the suits of red cards
+      --compact         draw each card on one line instead of in a box
+      --ascii           write suits as S, H, D and C
+  -h, --help            show this help";
+
+// How to set up the game, from the command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub players: u8,
+    pub cards: u8,
+    pub names: Vec<String>,
+    pub seed: Option<u64>,
+    pub rules: Rules,
+    // probability table for hints
+    pub table: Option<PathBuf>,
+    pub seats: Vec<(u8, Seat)>,
+    // play in the full-screen UI
+    pub tui: bool,
+    // clear the screen and wait for the next player between turns
+    pub hotseat: bool,
+    // how to draw the cards; the width is the terminal's
+    pub render: RenderOptions,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            players: 2,
+            cards: 5,
+            names: vec![],
+            seed: None,
+            rules: Rules::default(),
+            table: None,
+            seats: vec![],
+            tui: false,
+            hotseat: false,
+            render: RenderOptions::default(),
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    // parses the arguments after the program name, reporting the first
+    // problem found
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut i = 0;
+        while i < args.len() {
+            let flag = args[i].as_str();
+            if flag == "-h" || flag == "--help" {
+                options.help = true;
+                return Ok(options);
+            }
+            let switch = match flag {
+                "--tui" => Some(&mut options.tui),
+                "--hotseat" => Some(&mut options.hotseat),
+                "--color" => Some(&mut options.render.color),
+                "--compact" => Some(&mut options.render.compact),
+                "--ascii" => Some(&mut options.render.ascii),
+                _ => None,
+            };
+            if let Some(switch) = switch {
+                *switch = true;
+                i += 1;
+                continue;
+            }
+            let value = match args.get(i + 1) {
+                Some(value) => value.as_str(),
+                None => return Err(format!("{} needs a value", flag)),
+            };
+            match flag {
+                "-p" | "--players" => options.players = number(flag, value)?,
+                "-c" | "--cards" => options.cards = number(flag, value)?,
+                "-s" | "--seed" => options.seed = Some(number(flag, value)?),
+                "--max-hand" => options.rules.max_hand = number(flag, value)?,
+                "-t" | "--table" => options.table = Some(PathBuf::from(value)),
+                "-n" | "--names" => {
+                    options.names = value.split(',').map(|s| s.trim().to_owned()).collect();
+                }
+                "-v" | "--variant" => {
+                    options.rules.penalty = match value {
+                        "lose" => Penalty::LoseCard,
+                        "gain" => Penalty::GainCard,
+                        _ => return Err(format!("unknown variant: {} (lose or gain)", value)),
+                    }
+                }
+                "-u" | "--undo" => {
+                    options.rules.undo = match value {
+                        "always" => UndoPolicy::Always,
+                        "last" => UndoPolicy::LastMove,
+                        "never" => UndoPolicy::Never,
+                        _ => return Err(format!("unknown undo rule: {} (always, last or never)", value)),
+                    }
+                }
+                "-b" | "--bot" => {
+                    let (seat, kind) = match value.find('=') {
+                        Some(at) => (&value[..at], &value[at + 1..]),
+                        None => return Err(format!("--bot needs <seat>=<bot>, got {}", value)),
+                    };
+                    let seat: u8 = number("--bot", seat)?;
+                    let seat_kind = if kind == "human" {
+                        Seat::Human
+                    } else {
+                        kind.parse::<BotKind>()?.seat()
+                    };
+                    options.seats.push((seat, seat_kind));
+                }
+                _ => return Err(format!("unknown option: {}", flag)),
+            }
+            i += 2;
+        }
+        options.validate()?;
+        Ok(options)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.tui && self.hotseat {
+            return Err("--tui and --hotseat cannot be used together".to_owned());
+        }
+        if self.rules.penalty == Penalty::GainCard && self.rules.max_hand < self.cards {
+            return Err(format!("--max-hand {} is smaller than --cards {}",
+                               self.rules.max_hand,
+                               self.cards));
+        }
+        self.rules.check_table(self.players, self.cards)?;
+        if self.names.len() > self.players as usize {
+            return Err(format!("{} names given for {} players", self.names.len(), self.players));
+        }
+        for (i, name) in self.names.iter().enumerate() {
+            if name.is_empty() {
+                return Err("player names cannot be empty".to_owned());
+            }
+            if self.names[..i].contains(name) {
+                return Err(format!("two players are named {}", name));
+            }
+        }
+        for &(seat, _) in &self.seats {
+            if seat >= self.players {
+                return Err(format!("there is no seat {}; seats are 0 to {}", seat, self.players - 1));
+            }
+        }
+        Ok(())
+    }
+
+    // returns the options for a new game of players with cards each, keeping
+    // the rules, seed and the names and seats that are still at the table
+    pub fn with_table(&self, players: u8, cards: u8) -> Result<Options, String> {
+        let mut options = self.clone();
+        options.players = players;
+        options.cards = cards;
+        options.names.truncate(players as usize);
+        options.seats.retain(|&(seat, _)| seat < players);
+        options.validate()?;
+        Ok(options)
+    }
+
+    // returns how to draw cards, wrapping them at the current width of the
+    // terminal
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            width: Some(term::width()),
+            ..self.render
+        }
+    }
+
+    // returns the game described by the options
+    pub fn start(&self) -> GameState {
+        let seed = self.seed.unwrap_or_else(GameRng::random_seed);
+        // validate() made sure the hands fit in the deck
+        let mut state = GameState::seeded(self.players, self.cards, self.rules, seed).unwrap();
+        state.names = self.names.clone();
+        for &(seat, ref kind) in &self.seats {
+            state.set_seat(seat, kind.clone());
+        }
+        state
+    }
+}
+
+pub fn number<T: ::std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} needs a number, got {}", flag, value))
+}
+
+#[cfg(test)]
+mod test {
+    use bullpoker::game::{Penalty, UndoPolicy};
+    use bullpoker::strategy::BotKind;
+    use cli::Options;
+
+    fn parse(line: &str) -> Result<Options, String> {
+        let args: Vec<String> = line.split_whitespace().map(|s| s.to_owned()).collect();
+        Options::parse(&args)
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse("-p 3 --cards 4 --names ann,bo,cy --seed 7 --variant gain \
+                             --max-hand 6 --hotseat --undo last --bot 2=hard --color --ascii \
+                             --table probs.bin")
+            .unwrap();
+        assert_eq!(options.players, 3);
+        assert_eq!(options.cards, 4);
+        assert_eq!(options.names, vec!["ann", "bo", "cy"]);
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.rules.penalty, Penalty::GainCard);
+        assert_eq!(options.rules.max_hand, 6);
+        assert_eq!(options.rules.undo, UndoPolicy::LastMove);
+        assert!(options.hotseat);
+        assert_eq!(options.table, Some("probs.bin".into()));
+        assert!(options.render.color && options.render.ascii && !options.render.compact);
+        assert_eq!(options.seats, vec![(2, "hard".parse::<BotKind>().unwrap().seat())]);
+        let state = options.start();
+        assert_eq!(state.player_name(1), "bo");
+        assert_eq!(state.players[2].seat, options.seats[0].1);
+        assert_eq!(parse("").unwrap(), Options::default());
+        assert!(parse("--help --bogus").unwrap().help);
+        assert!(parse("--tui -p 3").unwrap().tui);
+    }
+
+    #[test]
+    fn test_new_table_keeps_the_options() {
+        let options = parse("-p 3 --names ann,bo,cy --seed 7 --variant gain --max-hand 8 \
+                             --bot 2=hard --bot 1=easy")
+            .unwrap();
+        let smaller = options.with_table(2, 3).unwrap();
+        assert_eq!(smaller.rules, options.rules);
+        assert_eq!(smaller.seed, Some(7));
+        assert_eq!(smaller.names, vec!["ann", "bo"]);
+        assert_eq!(smaller.seats, vec![(1, "easy".parse::<BotKind>().unwrap().seat())]);
+        let state = smaller.start();
+        assert_eq!(state.players.len(), 2);
+        assert_eq!(state.players[1].seat, smaller.seats[0].1);
+        // the seed replays the same deal
+        let again = options.with_table(2, 3).unwrap().start();
+        assert_eq!(state.players[0].hand.cards, again.players[0].hand.cards);
+        // gaining cards, 7 hands of up to 8 cards do not fit in the deck
+        assert!(options.with_table(7, 3).is_err());
+        assert!(options.with_table(1, 3).is_err());
+        assert!(options.with_table(3, 9).is_err());
+    }
+
+    #[test]
+    fn test_reject_bad_options() {
+        for line in &["-p 1",
+                      "-p 11",
+                      "-p 10 -c 5 --variant gain --max-hand 6",
+                      "-c 6 --variant gain",
+                      "-n a,b,c",
+                      "-n a,a",
+                      "--bot 2=hard",
+                      "--bot 1=genius",
+                      "--bot hard",
+                      "--seed x",
+                      "--cards",
+                      "--colour red",
+                      "--tui --hotseat",
+                      "--undo sometimes"] {
+            assert!(parse(line).is_err(), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use rand::Rng;
+
+use game::{Event, GameMove, GameState, PlayerView, Rules};
+use poker::{self, HandValue};
+use rng::GameRng;
+use strategy::threshold::{Difficulty, ThresholdBot};
+use strategy::{BotKind, Strategy};
+
+// Action 0 calls and action i bets HandValue::ladder()[i - 1]
+pub const CALL_ACTION: usize = 0;
+pub const LADDER_LEN: usize = poker::LADDER_LEN;
+pub const ACTIONS: usize = LADDER_LEN + 1;
+
+// The observation holds this many of the latest bets of the round
+pub const HISTORY_LEN: usize = 8;
+
+// The game an Env plays. The agent is player 0 and every other player is a
+// bot of the matching kind in `opponents`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvConfig {
+    pub cards: u8,
+    pub rules: Rules,
+    pub opponents: Vec<BotKind>,
+    pub rewards: Rewards,
+}
+
+impl Default for EnvConfig {
+    fn default() -> EnvConfig {
+        EnvConfig {
+            cards: 3,
+            rules: Rules::default(),
+            opponents: vec![BotKind::Threshold(ThresholdBot::preset(Difficulty::Medium))],
+            rewards: Rewards::default(),
+        }
+    }
+}
+
+impl EnvConfig {
+    pub fn players(&self) -> usize {
+        self.opponents.len() + 1
+    }
+
+    pub fn observation_len(&self) -> usize {
+        observation_len(self.players())
+    }
+}
+
+// Returns the length of every observation of a game with `players` players:
+//   52 own cards, one card count per seat, the current bet over the ladder,
+//   HISTORY_LEN bets of (made, ladder position, bettor seat), and the total
+//   number of cards.
+// Seats are counted from the observer, so seat 0 is always their own.
+pub fn observation_len(players: usize) -> usize {
+    52 + players + LADDER_LEN + HISTORY_LEN * (2 + players) + 1
+}
+
+// What the agent is paid during an episode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rewards {
+    // every call the agent loses
+    pub lost_call: f64,
+    // the episode ends with the agent as the last player standing
+    pub won_game: f64,
+    // the episode ends with the agent out of the game
+    pub knocked_out: f64,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards {
+            lost_call: -0.1,
+            won_game: 1.0,
+            knocked_out: -1.0,
+        }
+    }
+}
+
+// The result of one step
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub observation: Vec<f32>,
+    pub reward: f64,
+    pub done: bool,
+    pub mask: Vec<bool>,
+}
+
+// A BullPoker game seen by one agent, for reinforcement learning. Opponents
+// move inside step(), so every observation is taken on the agent's turn.
+pub struct Env {
+    pub config: EnvConfig,
+    state: Option<GameState>,
+    bots: Vec<Box<dyn Strategy>>,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig) -> Env {
+        Env {
+            config,
+            state: None,
+            bots: vec![],
+        }
+    }
+
+    // the game being played, once reset() has been called
+    pub fn state(&self) -> Option<&GameState> {
+        self.state.as_ref()
+    }
+
+    // starts a new episode dealt from the seed and returns the first
+    // observation. Returns None if the hands do not fit in the deck.
+    pub fn reset(&mut self, seed: u64) -> Option<Step> {
+        let players = self.config.players() as u8;
+        let state = GameState::seeded(players, self.config.cards, self.config.rules, seed)?;
+        let mut rng = GameRng::from_seed(seed);
+        self.bots = self.config.opponents.iter().map(|k| k.build(rng.next_u64())).collect();
+        self.state = Some(state);
+        Some(self.advance(0.0))
+    }
+
+    // Makes the agent's move and lets the opponents play until it is the
+    // agent's turn again or the episode is over. Returns None for a masked
+    // action or when there is no episode running.
+    pub fn step(&mut self, action: usize) -> Option<Step> {
+        let state = self.state.take()?;
+        if self.done(&state) || !self.legal(&state, action) {
+            self.state = Some(state);
+            return None;
+        }
+        let start = state.history.len();
+        let state = state.handle_gamemove(action_move(action));
+        let reward = self.call_reward(&state, start);
+        self.state = Some(state);
+        Some(self.advance(reward))
+    }
+
+    // returns the current observation of the agent
+    pub fn observation(&self) -> Vec<f32> {
+        match self.state {
+            Some(ref state) => self.encode(state),
+            None => vec![0.0; self.config.observation_len()],
+        }
+    }
+
+    // returns which actions are legal for the agent now
+    pub fn mask(&self) -> Vec<bool> {
+        match self.state {
+            Some(ref state) if !self.done(state) => {
+                (0..ACTIONS).map(|a| self.legal(state, a)).collect()
+            }
+            _ => vec![false; ACTIONS],
+        }
+    }
+
+    // plays the opponents until the agent has to move, then observes
+    fn advance(&mut self, mut reward: f64) -> Step {
+        let mut state = self.state.take().unwrap();
+        while !self.done(&state) && state.current_turn != 0 {
+            let view = state.view(state.current_turn);
+            let bot = &mut self.bots[view.player as usize - 1];
+            let gm = bot.choose(&view, &state.rules);
+            let start = state.history.len();
+            state = state.handle_gamemove(gm);
+            reward += self.call_reward(&state, start);
+        }
+        let done = self.done(&state);
+        if done {
+            reward += if state.winner() == Some(0) {
+                self.config.rewards.won_game
+            } else {
+                self.config.rewards.knocked_out
+            };
+        }
+        self.state = Some(state);
+        Step {
+            observation: self.observation(),
+            reward,
+            done,
+            mask: self.mask(),
+        }
+    }
+
+    // the episode ends when the agent is out or the game is over
+    fn done(&self, state: &GameState) -> bool {
+        state.winner().is_some() || state.players[0].name != 0
+    }
+
+    fn legal(&self, state: &GameState, action: usize) -> bool {
+        if action == CALL_ACTION {
+            return state.current_bet.is_some();
+        }
+        action <= LADDER_LEN && state.current_bet.is_none_or(|bet| ladder_bet(action) > bet)
+    }
+
+    // returns the reward for calls the agent lost since the history position
+    fn call_reward(&self, state: &GameState, start: usize) -> f64 {
+        state.history[start..]
+            .iter()
+            .filter(|e| match **e {
+                Event::Call { caller, bettor, present, .. } => {
+                    (if present { caller } else { bettor }) == 0
+                }
+                _ => false,
+            })
+            .count() as f64 * self.config.rewards.lost_call
+    }
+
+    fn encode(&self, state: &GameState) -> Vec<f32> {
+        let mut view = state.view(0);
+        if state.players[0].name != 0 {
+            // the agent is out; observe the table with an empty hand
+            view.player = 0;
+            view.hand.cards.clear();
+        }
+        encode_view(&view, self.config.players())
+    }
+}
+
+// encodes what a player sees in a game that started with `players` players
+pub fn encode_view(view: &PlayerView, players: usize) -> Vec<f32> {
+    let mut output = Vec::with_capacity(observation_len(players));
+    let mut cards = [0.0; 52];
+    for card in &view.hand.cards {
+        cards[card.to_index() as usize] = 1.0;
+    }
+    output.extend_from_slice(&cards);
+    let mut counts = vec![0.0; players];
+    for &(name, count) in &view.counts {
+        counts[seat_of(name, view.player, players)] = count as f32;
+    }
+    output.extend_from_slice(&counts);
+    let mut current = vec![0.0; LADDER_LEN];
+    if let Some(index) = view.current_bet.and_then(|bet| bet.ladder_index()) {
+        current[index] = 1.0;
+    }
+    output.extend_from_slice(&current);
+    output.extend(encode_bets(view, players));
+    output.push(view.total_cards() as f32);
+    output
+}
+
+// returns the move of an action index
+pub fn action_move(action: usize) -> GameMove {
+    if action == CALL_ACTION {
+        GameMove::Call()
+    } else {
+        GameMove::Bet(ladder_bet(action))
+    }
+}
+
+// returns the action index of a move; NewGame has none
+pub fn move_action(gm: &GameMove) -> Option<usize> {
+    match *gm {
+        GameMove::Call() => Some(CALL_ACTION),
+        GameMove::Bet(hv) => hv.ladder_index().map(|i| i + 1),
+        GameMove::NewGame(..) => None,
+    }
+}
+
+// returns the bet of a bet action. The ladder is built once, as the mask
+// looks up every action on every step.
+fn ladder_bet(action: usize) -> HandValue {
+    HandValue::shared_ladder()[action - 1]
+}
+
+// returns the seat of a player counted from the viewer
+fn seat_of(name: u8, viewer: u8, players: usize) -> usize {
+    (name as usize + players - viewer as usize % players) % players
+}
+
+// the latest bets of the round, newest first, padded with zeros
+fn encode_bets(view: &PlayerView, players: usize) -> Vec<f32> {
+    let mut output = vec![];
+    for &(player, bet) in view.round_bets().iter().rev().take(HISTORY_LEN) {
+        let mut seat = vec![0.0; players];
+        seat[seat_of(player, view.player, players)] = 1.0;
+        output.push(1.0);
+        output.push(bet.ladder_index().map_or(0.0, |i| (i + 1) as f32 / LADDER_LEN as f32));
+        output.extend(seat);
+    }
+    output.resize(HISTORY_LEN * (2 + players), 0.0);
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use env::{Env, EnvConfig, ACTIONS, move_action, action_move};
+    use rng::GameRng;
+    use strategy::BotKind;
+
+    #[test]
+    fn test_env_plays_masked_episodes() {
+        let config = EnvConfig {
+            opponents: vec![BotKind::Caller, BotKind::MinRaise],
+            ..EnvConfig::default()
+        };
+        let len = config.observation_len();
+        let mut env = Env::new(config);
+        let mut rng = GameRng::from_seed(4);
+        let mut total = 0.0;
+        for episode in 0..20 {
+            let mut step = env.reset(episode).unwrap();
+            while !step.done {
+                assert_eq!(step.observation.len(), len);
+                assert_eq!(step.mask.len(), ACTIONS);
+                let legal: Vec<usize> = (0..ACTIONS).filter(|&a| step.mask[a]).collect();
+                // the mask matches the engine's legal moves
+                let view = env.state().unwrap().view(0);
+                let moves: Vec<usize> = view.legal_moves().iter().filter_map(move_action).collect();
+                assert_eq!(legal, moves);
+                // masked actions are refused
+                if !step.mask[0] {
+                    assert!(env.step(0).is_none());
+                }
+                let action = *rng.choose(&legal).unwrap();
+                assert_eq!(move_action(&action_move(action)), Some(action));
+                step = env.step(action).unwrap();
+                total += step.reward;
+            }
+            assert!(env.step(1).is_none());
+            assert!(env.mask().iter().all(|&legal| !legal));
+        }
+        assert!(total != 0.0);
+        // the same seed gives the same episode
+        let first = env.reset(3).unwrap();
+        assert_eq!(env.reset(3).unwrap(), first);
+    }
+}
@@ -1,11 +1,21 @@
-use std::collections::HashMap;
-
+use card::RenderOptions;
 use poker::{Hand, HandValue};
+use rng::GameRng;
 
 #[derive(Debug, Clone)]
 pub struct Player {
     pub name: u8,
     pub hand: Hand,
+    pub seat: Seat,
+}
+
+// Who makes the moves for a player
+#[derive(Clone, Debug, PartialEq)]
+pub enum Seat {
+    Human,
+    // a computer player, by the name of its bot as given to
+    // strategy::BotKind::from_str, e.g. "hard" or "ext:./mybot"
+    Bot(String),
 }
 
 #[derive(Clone, Debug)]
@@ -13,14 +23,173 @@ pub struct GameState {
     pub players: Vec<Player>,
     pub current_turn: usize,
     pub current_bet: Option<HandValue>,
+    pub history: Vec<Event>,
+    pub rules: Rules,
+    // names shown for the players, by player; "Player N" when missing
+    pub names: Vec<String>,
+    // deals every round, so a seeded game always deals the same cards
+    pub rng: GameRng,
+}
+
+// Something that happened during the game, recorded in GameState::history
+#[derive(Clone, Debug)]
+pub enum Event {
+    // the hands of every player at the start of a round
+    Deal(Vec<(u8, Hand)>),
+    // a player bet that the handvalue is among all of the cards
+    Bet(u8, HandValue),
+    // a player called the previous bet; present is true iff the bet was there
+    Call {
+        caller: u8,
+        bettor: u8,
+        bet: HandValue,
+        present: bool,
+    },
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum GameMove {
     NewGame(u8, u8),
     Bet(HandValue),
     Call(),
 }
 
+// What happens to the player who loses a call
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Penalty {
+    // the loser plays the next round with one card less and is out of the
+    // game after losing their last card
+    LoseCard,
+    // the loser plays the next round with one card more and is out of the
+    // game after losing while holding Rules::max_hand cards
+    GainCard,
+}
+
+// Which moves can be taken back
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UndoPolicy {
+    // any move, back to the start of the game
+    Always,
+    // only the last move, until the next player acts
+    LastMove,
+    // none, for competitive play
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Rules {
+    pub penalty: Penalty,
+    pub max_hand: u8,
+    pub undo: UndoPolicy,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            penalty: Penalty::LoseCard,
+            max_hand: 5,
+            undo: UndoPolicy::Always,
+        }
+    }
+}
+
+impl Rules {
+    // change in the number of cards of the player who loses a call
+    pub fn loser_delta(&self) -> i8 {
+        match self.penalty {
+            Penalty::LoseCard => -1,
+            Penalty::GainCard => 1,
+        }
+    }
+
+    // Checks that a game of players with cards each can be dealt: every
+    // player must be able to hold their largest hand at once
+    pub fn check_table(&self, players: u8, cards: u8) -> Result<(), String> {
+        if players < 2 {
+            return Err("a game needs at least 2 players".to_owned());
+        }
+        if cards < 1 {
+            return Err("players need at least 1 card".to_owned());
+        }
+        let largest = match self.penalty {
+            Penalty::LoseCard => cards,
+            Penalty::GainCard => {
+                if self.max_hand < cards {
+                    return Err(format!("hands of {} cards are over the largest hand of {}",
+                                       cards,
+                                       self.max_hand));
+                }
+                self.max_hand
+            }
+        };
+        if players as usize * largest as usize > 52 {
+            return Err(format!("{} players with up to {} cards each need more than the 52 cards \
+                                of the deck",
+                               players,
+                               largest));
+        }
+        Ok(())
+    }
+}
+
+// What one player knows about the game: their own cards, how many cards
+// everyone holds and what has been bet
+#[derive(Clone, Debug)]
+pub struct PlayerView {
+    pub player: u8,
+    pub hand: Hand,
+    // name and number of cards of every player, in turn order
+    pub counts: Vec<(u8, usize)>,
+    pub current_turn: usize,
+    pub current_bet: Option<HandValue>,
+    // the game history, where the deal of the current round only shows your
+    // own hand
+    pub history: Vec<Event>,
+}
+
+impl PlayerView {
+    // returns every move the player can make: a call if there is a bet, and
+    // every bet higher than the current one
+    pub fn legal_moves(&self) -> Vec<GameMove> {
+        let mut output = vec![];
+        if self.current_bet.is_some() {
+            output.push(GameMove::Call());
+        }
+        for &hv in HandValue::shared_ladder() {
+            if self.current_bet.is_none_or(|bet| hv > bet) {
+                output.push(GameMove::Bet(hv));
+            }
+        }
+        output
+    }
+
+    // returns the lowest bet above the current one, if there is any
+    pub fn min_raise(&self) -> Option<HandValue> {
+        HandValue::shared_ladder()
+            .iter()
+            .find(|&&hv| self.current_bet.is_none_or(|bet| hv > bet))
+            .cloned()
+    }
+
+    // returns the number of cards held by all players
+    pub fn total_cards(&self) -> u8 {
+        self.counts.iter().map(|&(_, count)| count as u8).sum()
+    }
+
+    // returns the bets made since the last deal, in order
+    pub fn round_bets(&self) -> Vec<(u8, HandValue)> {
+        let mut output = vec![];
+        for event in &self.history {
+            match *event {
+                Event::Deal(_) => output.clear(),
+                Event::Bet(player, bet) => output.push((player, bet)),
+                Event::Call { .. } => {}
+            }
+        }
+        output
+    }
+}
+
 impl GameState {
     pub fn handle_gamemove(mut self, gm: GameMove) -> GameState {
         match gm {
@@ -32,6 +201,8 @@ impl GameState {
                 }
             }
             GameMove::Bet(hv) => {
+                let name = self.players[self.current_turn].name;
+                self.history.push(Event::Bet(name, hv));
                 self.turn_after(hv);
                 return self.clone();
             }
@@ -43,26 +214,98 @@ impl GameState {
     }
 
     pub fn init_game(num_players: u8, init_handsize: u8) -> Option<GameState> {
-        if num_players * init_handsize > 52 {
-            return None;
-        }
+        GameState::with_rules(num_players, init_handsize, Rules::default())
+    }
+
+    pub fn with_rules(num_players: u8, init_handsize: u8, rules: Rules) -> Option<GameState> {
+        GameState::seeded(num_players, init_handsize, rules, GameRng::random_seed())
+    }
+
+    // starts a game whose deals are determined by the seed
+    pub fn seeded(num_players: u8, init_handsize: u8, rules: Rules, seed: u64)
+        -> Option<GameState> {
+        rules.check_table(num_players, init_handsize).ok()?;
+        let mut rng = GameRng::from_seed(seed);
         let mut players = vec![];
-        let mut deck = Hand::get_full_deck();
-        let mut turn_order = vec![];
+        let mut deck = Hand::get_shuffled_deck(&mut rng);
 
         for i in 0..num_players {
             let new_player = Player {
                 name: i,
                 hand: Hand::hand_from(&mut deck, init_handsize as usize),
+                seat: Seat::Human,
             };
             players.push(new_player);
-            turn_order.push(i);
         }
-        Some(GameState {
+        let mut state = GameState {
             players: players,
             current_turn: 0,
             current_bet: None,
-        })
+            history: vec![],
+            rules,
+            names: vec![],
+            rng,
+        };
+        state.record_deal();
+        Some(state)
+    }
+
+    // sets who makes the moves for the named player
+    pub fn set_seat(&mut self, name: u8, seat: Seat) {
+        for player in &mut self.players {
+            if player.name == name {
+                player.seat = seat.clone();
+            }
+        }
+    }
+
+    // returns the name shown for the player
+    pub fn player_name(&self, name: u8) -> String {
+        match self.names.get(name as usize) {
+            Some(shown) if !shown.is_empty() => shown.clone(),
+            _ => format!("Player {}", name),
+        }
+    }
+
+    // returns the name of the last player standing once the game is over
+    pub fn winner(&self) -> Option<u8> {
+        if self.players.len() == 1 {
+            Some(self.players[0].name)
+        } else {
+            None
+        }
+    }
+
+    // returns what the player at the input index knows about the game
+    pub fn view(&self, index: usize) -> PlayerView {
+        let player = &self.players[index];
+        let mut history = self.history.clone();
+        // hands of the current round stay hidden until the next call
+        if let Some(i) = history.iter().rposition(|e| matches!(*e, Event::Deal(_))) {
+            if let Event::Deal(ref mut hands) = history[i] {
+                hands.retain(|&(name, _)| name == player.name);
+            }
+        }
+        PlayerView {
+            player: player.name,
+            hand: player.hand.clone(),
+            counts: self.players
+                .iter()
+                .map(|p| (p.name, p.hand.cards.len()))
+                .collect(),
+            current_turn: self.current_turn,
+            current_bet: self.current_bet,
+            history,
+        }
+    }
+
+    // records the current hands in the history
+    fn record_deal(&mut self) {
+        let hands = self.players
+            .iter()
+            .map(|p| (p.name, p.hand.clone()))
+            .collect();
+        self.history.push(Event::Deal(hands));
     }
 
     fn turn_after(&mut self, new_bet: HandValue) {
@@ -76,102 +319,87 @@ impl GameState {
     // called when the player 'calls' the previous player
     // returns true iff the player successfully called the bluff
     fn handle_call(&mut self) -> bool {
-        if self.current_bet == None {
-            println!("No current bet!");
-        }
+        let bet = match self.current_bet {
+            Some(bet) => bet,
+            None => {
+                println!("No current bet!");
+                return false;
+            }
+        };
         // construct deck with all players hand
         let mut all_cards = Hand::empty_hand();
         for player in &self.players {
             all_cards.add_cards(player.hand.clone());
         }
-        if all_cards.contains_handvalue(&self.current_bet.unwrap()) {
-            // Calling bluff failed. Calling player loses a card
-            let hand_size = self.players
-                .get(self.current_turn)
-                .unwrap()
-                .hand
-                .cards
-                .len();
-            if hand_size == 1 {
-                // player out of game and person who knocked them out goes next
-                self.players.remove(self.current_turn);
-                self.current_turn -= 1;
-                if self.current_turn < 0 {
-                    self.current_turn = self.players.len() as usize - 1
-                }
-            } else {
-                // player loses a card
-                let index = self.current_turn;
-                self.remove_card_from(index);
-            }
-            self.current_bet = None;
-            false
+        let present = all_cards.contains_handvalue(&bet);
+        let previous = if self.current_turn == 0 {
+            self.players.len() - 1
         } else {
-            // Previous player loses a card
-            let previous_player = if self.current_turn == 0 {
-                self.players.len() - 1
-            } else {
-                self.current_turn - 1
-            };
-            let hand_size = self.players.get(previous_player).unwrap().hand.cards.len();
-            if hand_size == 1 {
-                // player out of game and person who knocked them out goes next
-                self.players.remove(previous_player);
-                if self.current_turn != 0 {
-                    self.current_turn -= 1;
-                }
-            } else {
-                // player loses a card
-                self.remove_card_from(previous_player);
-                if self.current_turn == 0 {
-                    self.current_turn = self.players.len() - 1;
-                } else {
-                    self.current_turn -= 1;
-                }
-            }
-            self.current_bet = None;
-            true
-        }
+            self.current_turn - 1
+        };
+        self.history.push(Event::Call {
+            caller: self.players[self.current_turn].name,
+            bettor: self.players[previous].name,
+            bet,
+            present,
+        });
+        let caller_won = self.resolve_call(present);
+        self.record_deal();
+        caller_won
     }
 
-    // Removes a card from the input players hand
-    fn remove_card_from(&mut self, index: usize) {
-        let mut new_players = vec![];
-        let mut i = 0;
-        for player in &self.players {
-            let hand_size = if i == index {
-                player.hand.cards.len() - 1
-            } else {
-                player.hand.cards.len()
-            };
-            let new_player = Player {
-                name: player.name.clone(),
-                hand: Hand::random_hand(hand_size),
-            };
-            new_players.push(new_player);
-            i += 1;
+    // applies the penalty of a call to the losing player and deals the next
+    // round. The loser starts the next round unless they are out of the game,
+    // in which case the player who knocked them out starts.
+    fn resolve_call(&mut self, present: bool) -> bool {
+        let previous = if self.current_turn == 0 {
+            self.players.len() - 1
+        } else {
+            self.current_turn - 1
+        };
+        let (loser, winner) = if present {
+            // Calling bluff failed. Calling player is penalized
+            (self.current_turn, previous)
+        } else {
+            (previous, self.current_turn)
+        };
+        let hand_size = self.players[loser].hand.cards.len();
+        let knocked_out = match self.rules.penalty {
+            Penalty::LoseCard => hand_size <= 1,
+            Penalty::GainCard => hand_size >= self.rules.max_hand as usize,
+        };
+        let mut sizes: Vec<usize> = self.players.iter().map(|p| p.hand.cards.len()).collect();
+        if knocked_out {
+            self.players.remove(loser);
+            sizes.remove(loser);
+            self.current_turn = if winner > loser { winner - 1 } else { winner };
+        } else {
+            sizes[loser] = (hand_size as i8 + self.rules.loser_delta()) as usize;
+            self.current_turn = loser;
         }
-        self.players = new_players;
+        self.current_bet = None;
+        self.deal(&sizes);
+        !present
     }
 
-    // Redeals cards
+    // Redeals cards, keeping the size of every hand
     fn redeal(&mut self) {
-        let mut new_players = vec![];
-        let mut deck = Hand::get_full_deck();
-        for player in &self.players {
-            let hand_size = player.hand.cards.len();
-            new_players.push(Player {
-                name: player.name.clone(),
-                hand: Hand::hand_from(&mut deck, hand_size),
-            });
+        let sizes: Vec<usize> = self.players.iter().map(|p| p.hand.cards.len()).collect();
+        self.deal(&sizes);
+    }
+
+    // Deals new hands of the input sizes from a fresh deck
+    fn deal(&mut self, sizes: &[usize]) {
+        let mut deck = Hand::get_shuffled_deck(&mut self.rng);
+        for (player, &hand_size) in self.players.iter_mut().zip(sizes) {
+            player.hand = Hand::hand_from(&mut deck, hand_size);
         }
-        self.players = new_players;
     }
 
-    pub fn display(&self) {
+    pub fn display(&self, opts: &RenderOptions) {
         for player in &self.players {
-            println!("Player {}: ", player.name);
-            println!("{}", player.hand.to_string());
+            println!("{}: ", self.player_name(player.name));
+            println!("{}", player.hand.render(opts));
         }
     }
 }
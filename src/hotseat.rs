@@ -0,0 +1,63 @@
+use std::io::{self, Write};
+
+use bullpoker::card::RenderOptions;
+use bullpoker::game::{Event, GameState};
+use bullpoker::reveal::Reveal;
+
+// Clears the screen and waits for the device to be passed to the player
+// about to move. Returns false when the input is closed.
+pub fn pass_to(state: &GameState) -> bool {
+    print!("\x1b[2J\x1b[H");
+    let name = state.player_name(state.players[state.current_turn].name);
+    println!("Pass to {}, press Enter", name);
+    wait_for_enter()
+}
+
+// prints what the player about to move may see: their own hand, the size of
+// every hand and the bets of the round
+pub fn show_turn(state: &GameState, opts: &RenderOptions) {
+    let view = state.view(state.current_turn);
+    print!("\x1b[2J\x1b[H");
+    println!("{}'s hand:", state.player_name(view.player));
+    println!("{}", view.hand.render(opts));
+    let counts: Vec<String> = view.counts
+        .iter()
+        .map(|&(name, count)| format!("{} {}", state.player_name(name), count))
+        .collect();
+    println!("Cards: {}", counts.join(", "));
+    let bets = view.round_bets();
+    if bets.is_empty() {
+        println!("No bets yet this round");
+    }
+    for &(player, bet) in &bets {
+        println!("{} bet {:?}", state.player_name(player), bet);
+    }
+}
+
+// Shows everyone the hands of the round that the last call ended, and who was
+// right. Waits for Enter so every player can see them before the screen is
+// cleared again. Does nothing if there has been no call.
+pub fn show_reveal(state: &GameState, opts: &RenderOptions) -> bool {
+    let reveal = match Reveal::last(state) {
+        Some(reveal) => reveal,
+        None => return true,
+    };
+    print!("\x1b[2J\x1b[H");
+    println!("{}", reveal.report(state, opts));
+    println!("Press Enter to continue");
+    wait_for_enter()
+}
+
+fn wait_for_enter() -> bool {
+    let _ = io::stdout().flush();
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => false,
+        Ok(_) => true,
+    }
+}
+
+// returns how many calls the game has had, to tell when a move was a call
+pub fn calls(state: &GameState) -> usize {
+    state.history.iter().filter(|e| matches!(**e, Event::Call { .. })).count()
+}
@@ -0,0 +1,274 @@
+use rand::Rng;
+
+use card::{Card, Rank};
+use game::Event;
+use poker::{Hand, HandValue};
+use rng::GameRng;
+
+// A model of how players bet, used to weigh the holdings that could have
+// produced an observed bet
+pub trait BetModel {
+    // Relative likelihood that a player holding `holding` raises `previous`
+    // to `bet`. Only ratios between holdings matter.
+    fn likelihood(&self, holding: &[Card], previous: Option<&HandValue>, bet: &HandValue)
+        -> f64;
+}
+
+// Assumes players bet on hands their own cards support. Every needed card a
+// player holds adds `support` to the weight of the bet; a pure bluff from a
+// player holding none of them still has weight `bluff`.
+#[derive(Clone, Copy, Debug)]
+pub struct SupportModel {
+    pub bluff: f64,
+    pub support: f64,
+}
+
+impl Default for SupportModel {
+    fn default() -> SupportModel {
+        SupportModel {
+            bluff: 0.25,
+            support: 1.0,
+        }
+    }
+}
+
+impl BetModel for SupportModel {
+    fn likelihood(&self, holding: &[Card], _previous: Option<&HandValue>, bet: &HandValue)
+        -> f64 {
+        self.bluff + self.support * supporting_cards(holding, bet) as f64
+    }
+}
+
+// returns how many of the cards needed by the handvalue are in the holding
+pub fn supporting_cards(holding: &[Card], hv: &HandValue) -> usize {
+    match hv.card_groups() {
+        Some(groups) => {
+            groups
+                .iter()
+                .map(|&(ref cards, need)| {
+                    let held = holding.iter().filter(|c| cards.contains(c)).count();
+                    if held > need { need } else { held }
+                })
+                .sum()
+        }
+        None => 0,
+    }
+}
+
+// One possible assignment of the unseen cards to the opponents
+#[derive(Clone, Debug)]
+struct Particle {
+    hands: Vec<Vec<Card>>,
+    weight: f64,
+}
+
+// Posterior over the opponents' holdings given your own hand and the bets
+// made so far in the round. The distribution is represented by sampled deals
+// of the unseen cards that are reweighted as bets are observed.
+#[derive(Clone, Debug)]
+pub struct Inference<M: BetModel> {
+    me: u8,
+    my_hand: Hand,
+    // names of the opponents, in the same order as Particle::hands
+    opponents: Vec<u8>,
+    particles: Vec<Particle>,
+    previous: Option<HandValue>,
+    model: M,
+    rng: GameRng,
+}
+
+impl<M: BetModel> Inference<M> {
+    // Starts from a uniform distribution over deals of the unseen cards.
+    // `opponents` lists each opponent's name and number of cards.
+    pub fn new(me: u8, my_hand: &Hand, opponents: &[(u8, usize)], model: M, samples: usize,
+               seed: u64) -> Inference<M> {
+        let mut inference = Inference {
+            me,
+            my_hand: my_hand.clone(),
+            opponents: opponents.iter().map(|&(name, _)| name).collect(),
+            particles: vec![],
+            previous: None,
+            model,
+            rng: GameRng::from_seed(seed),
+        };
+        let unseen: Vec<Card> = (0..52)
+            .map(|i| Card::from_index(i).unwrap())
+            .filter(|c| !my_hand.cards.contains(c))
+            .collect();
+        for _ in 0..samples {
+            let mut deck = unseen.clone();
+            inference.rng.shuffle(&mut deck);
+            let mut hands = vec![];
+            let mut start = 0;
+            for &(_, count) in opponents {
+                hands.push(deck[start..start + count].to_vec());
+                start += count;
+            }
+            inference.particles.push(Particle {
+                hands,
+                weight: 1.0 / samples as f64,
+            });
+        }
+        inference
+    }
+
+    // updates the distribution with a bet made by the input player
+    pub fn observe_bet(&mut self, player: u8, bet: &HandValue) {
+        let previous = self.previous;
+        self.previous = Some(*bet);
+        // your own bets say nothing about the other hands
+        let seat = match self.opponents.iter().position(|&name| name == player) {
+            Some(seat) => seat,
+            None => return,
+        };
+        for particle in &mut self.particles {
+            particle.weight *= self.model.likelihood(&particle.hands[seat], previous.as_ref(), bet);
+        }
+        self.normalize();
+        if self.effective_samples() < self.particles.len() as f64 / 2.0 {
+            self.resample();
+        }
+    }
+
+    // updates the distribution with every bet of the current round. The
+    // history may hold earlier rounds; only bets after the last deal count.
+    pub fn observe_history(&mut self, history: &[Event]) {
+        let start = history
+            .iter()
+            .rposition(|e| matches!(*e, Event::Deal(_)))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        for event in &history[start..] {
+            if let Event::Bet(player, ref bet) = *event {
+                self.observe_bet(player, bet);
+            }
+        }
+    }
+
+    // returns the posterior probability that the handvalue is among all of the
+    // cards in play
+    pub fn prob_present(&self, hv: &HandValue) -> f64 {
+        let mut pool = self.my_hand.clone();
+        let mut present = 0.0;
+        for particle in &self.particles {
+            pool.cards.truncate(self.my_hand.cards.len());
+            for hand in &particle.hands {
+                pool.cards.extend_from_slice(hand);
+            }
+            if pool.contains_handvalue(hv) {
+                present += particle.weight;
+            }
+        }
+        present
+    }
+
+    // returns the posterior probability that the player holds the card
+    pub fn prob_holds(&self, player: u8, card: &Card) -> f64 {
+        match self.opponents.iter().position(|&name| name == player) {
+            Some(seat) => {
+                self.particles
+                    .iter()
+                    .filter(|p| p.hands[seat].contains(card))
+                    .map(|p| p.weight)
+                    .sum()
+            }
+            None => if self.me == player && self.my_hand.cards.contains(card) { 1.0 } else { 0.0 },
+        }
+    }
+
+    // returns the posterior distribution of how many cards of the rank the
+    // player holds, indexed by count
+    pub fn rank_count_distribution(&self, player: u8, rank: Rank) -> [f64; 5] {
+        let mut output = [0.0; 5];
+        let seat = match self.opponents.iter().position(|&name| name == player) {
+            Some(seat) => seat,
+            None => {
+                let held = self.my_hand.cards.iter().filter(|c| c.rank == rank).count();
+                output[held] = 1.0;
+                return output;
+            }
+        };
+        for particle in &self.particles {
+            let held = particle.hands[seat].iter().filter(|c| c.rank == rank).count();
+            output[held] += particle.weight;
+        }
+        output
+    }
+
+    fn normalize(&mut self) {
+        let total: f64 = self.particles.iter().map(|p| p.weight).sum();
+        if total <= 0.0 {
+            // no sampled deal explains the bets, so start over from uniform
+            let uniform = 1.0 / self.particles.len() as f64;
+            for particle in &mut self.particles {
+                particle.weight = uniform;
+            }
+            return;
+        }
+        for particle in &mut self.particles {
+            particle.weight /= total;
+        }
+    }
+
+    fn effective_samples(&self) -> f64 {
+        let sum_sq: f64 = self.particles.iter().map(|p| p.weight * p.weight).sum();
+        if sum_sq > 0.0 { 1.0 / sum_sq } else { 0.0 }
+    }
+
+    // systematic resampling, which keeps likely deals and drops unlikely ones
+    fn resample(&mut self) {
+        let n = self.particles.len();
+        let step = 1.0 / n as f64;
+        let mut target = self.rng.next_f64() * step;
+        let mut cumulative = 0.0;
+        let mut output = Vec::with_capacity(n);
+        for particle in &self.particles {
+            cumulative += particle.weight;
+            while target < cumulative && output.len() < n {
+                output.push(Particle {
+                    hands: particle.hands.clone(),
+                    weight: step,
+                });
+                target += step;
+            }
+        }
+        // rounding can leave the last slots empty
+        while output.len() < n {
+            let last = output[output.len() - 1].clone();
+            output.push(last);
+        }
+        self.particles = output;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use card::{Card, Suit, Rank};
+    use inference::{Inference, SupportModel};
+    use poker::{Hand, HandValue};
+
+    #[test]
+    fn test_bet_raises_posterior() {
+        let hand = Hand {
+            cards: vec![Card {
+                rank: Rank::Two,
+                suit: Suit::Clubs,
+            }],
+        };
+        let mut inference =
+            Inference::new(0, &hand, &[(1, 3)], SupportModel::default(), 2000, 3);
+        let trips = HandValue::ThreeOfAKind(Rank::Queen);
+        let prior_present = inference.prob_present(&HandValue::OnePair(Rank::Queen));
+        let prior_holds = inference.rank_count_distribution(1, Rank::Queen)[0];
+        inference.observe_bet(1, &trips);
+        let posterior_present = inference.prob_present(&HandValue::OnePair(Rank::Queen));
+        let posterior_holds = inference.rank_count_distribution(1, Rank::Queen)[0];
+        assert!(posterior_present > prior_present);
+        // less likely to hold no queens after betting three of them
+        assert!(posterior_holds < prior_holds);
+        // your own bets do not move the distribution
+        let before = inference.prob_present(&trips);
+        inference.observe_bet(0, &HandValue::FourOfAKind(Rank::Queen));
+        assert_eq!(before, inference.prob_present(&trips));
+    }
+}
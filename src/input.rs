@@ -0,0 +1,409 @@
+use std::io::{self, IsTerminal, Read, Write};
+
+use bullpoker::card::{Card, Rank, Suit};
+use bullpoker::game::{GameMove, PlayerView, Seat};
+use bullpoker::poker::HandValue;
+use bullpoker::strategy::BotKind;
+use bullpoker::term;
+use bullpoker::tui::Key;
+
+pub const COMMANDS: [&str; 10] = ["new", "seat", "bet", "call", "hint", "undo", "redo", "save",
+                                  "load", "quit"];
+
+pub const HAND_TYPES: [&str; 9] = ["high", "pair", "twopair", "triple", "straight", "flush",
+                                   "fullhouse", "quad", "straightflush"];
+
+// What is being typed, which decides what tab completes
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Context {
+    // a command, which may be a whole bet, e.g. "bet pair A"
+    Command,
+    // a bet, e.g. "pair A"
+    Bet,
+    // anything else
+    Plain,
+}
+
+// Reads lines with editing, history and tab completion when the input is a
+// terminal, and plain lines otherwise.
+//
+// Keys: left/right move, up/down walk the history, tab completes, backspace,
+// ctrl-a/ctrl-e go to the start/end, ctrl-u clears the line and ctrl-c or
+// ctrl-d on an empty line end the input.
+pub struct Editor {
+    history: Vec<String>,
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        Editor { history: vec![] }
+    }
+
+    // Prints the prompt and reads a line that passes the check. A line that
+    // fails is explained and can be corrected. Returns None at the end of
+    // the input.
+    pub fn read_line(&mut self,
+                     prompt: &str,
+                     context: Context,
+                     check: &dyn Fn(&str) -> Result<(), String>)
+                     -> Option<String> {
+        let mut line = String::new();
+        loop {
+            line = if io::stdin().is_terminal() {
+                self.edit(prompt, context, &line)?
+            } else {
+                read_plain(prompt)?
+            };
+            let trimmed = line.trim().to_owned();
+            if !trimmed.is_empty() && self.history.last() != Some(&trimmed) {
+                self.history.push(trimmed.clone());
+            }
+            match check(&trimmed) {
+                Ok(()) => return Some(trimmed),
+                Err(error) => println!("{}", error),
+            }
+        }
+    }
+
+    // edits the line in raw mode, starting from the input text
+    fn edit(&mut self, prompt: &str, context: Context, start: &str) -> Option<String> {
+        let raw = match term::RawMode::enter() {
+            Some(raw) => raw,
+            None => return read_plain(prompt),
+        };
+        let mut line: Vec<char> = start.chars().collect();
+        let mut cursor = line.len();
+        // the history entry shown, history.len() for the line being typed
+        let mut recalled = self.history.len();
+        let mut typed = line.clone();
+        let mut out = io::stdout();
+        let mut buf = [0; 64];
+        redraw(&mut out, prompt, &line, cursor);
+        loop {
+            let n = match io::stdin().read(&mut buf) {
+                Ok(0) | Err(_) => 0,
+                Ok(n) => n,
+            };
+            if n == 0 {
+                drop(raw);
+                println!();
+                return None;
+            }
+            for key in Key::parse(&buf[..n]) {
+                match key {
+                    Key::Enter => {
+                        drop(raw);
+                        println!();
+                        return Some(line.into_iter().collect());
+                    }
+                    Key::Char('\u{3}') => {
+                        drop(raw);
+                        println!();
+                        return None;
+                    }
+                    Key::Char('\u{4}') if line.is_empty() => {
+                        drop(raw);
+                        println!();
+                        return None;
+                    }
+                    Key::Char('\u{7f}') | Key::Char('\u{8}') if cursor > 0 => {
+                        cursor -= 1;
+                        line.remove(cursor);
+                    }
+                    Key::Char('\u{1}') => cursor = 0,
+                    Key::Char('\u{5}') => cursor = line.len(),
+                    Key::Char('\u{15}') => {
+                        line.clear();
+                        cursor = 0;
+                    }
+                    Key::Left => cursor = cursor.saturating_sub(1),
+                    Key::Right => cursor = (cursor + 1).min(line.len()),
+                    Key::Up | Key::Down => {
+                        if recalled == self.history.len() {
+                            typed = line.clone();
+                        }
+                        recalled = match key {
+                            Key::Up => recalled.saturating_sub(1),
+                            _ => (recalled + 1).min(self.history.len()),
+                        };
+                        line = match self.history.get(recalled) {
+                            Some(entry) => entry.chars().collect(),
+                            None => typed.clone(),
+                        };
+                        cursor = line.len();
+                    }
+                    Key::Char('\t') => {
+                        let before: String = line[..cursor].iter().collect();
+                        let options = completions(context, &before);
+                        let partial = before.chars().rev().take_while(|&c| c != ' ').count();
+                        let start = cursor - partial;
+                        let common = common_prefix(&options);
+                        let replacement = if options.len() == 1 {
+                            format!("{} ", options[0])
+                        } else if common.chars().count() > partial {
+                            common
+                        } else {
+                            if options.len() > 1 {
+                                print!("\r\n{}\r\n", options.join("  "));
+                            }
+                            continue;
+                        };
+                        line.splice(start..cursor, replacement.chars());
+                        cursor = start + replacement.chars().count();
+                    }
+                    Key::Char(c) if !c.is_control() => {
+                        line.insert(cursor, c);
+                        cursor += 1;
+                    }
+                    _ => {}
+                }
+            }
+            redraw(&mut out, prompt, &line, cursor);
+        }
+    }
+}
+
+// rewrites the prompt and the line, and puts the cursor in place
+fn redraw<W: Write>(out: &mut W, prompt: &str, line: &[char], cursor: usize) {
+    let text: String = line.iter().collect();
+    let _ = write!(out, "\r{}{}\x1b[K", prompt, text);
+    if cursor < line.len() {
+        let _ = write!(out, "\x1b[{}D", line.len() - cursor);
+    }
+    let _ = out.flush();
+}
+
+fn read_plain(prompt: &str) -> Option<String> {
+    print!("{}", prompt);
+    let _ = io::stdout().flush();
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input),
+    }
+}
+
+fn common_prefix(options: &[String]) -> String {
+    let first = match options.first() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let mut len = first.len();
+    for option in options {
+        len = first.chars()
+            .zip(option.chars())
+            .take_while(|&(a, b)| a.eq_ignore_ascii_case(&b))
+            .count()
+            .min(len);
+    }
+    first[..len].to_owned()
+}
+
+// Returns the words that can complete the last word of the text, which is
+// empty if the text ends with a space
+pub fn completions(context: Context, text: &str) -> Vec<String> {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    let partial = if text.ends_with(' ') || text.is_empty() {
+        ""
+    } else {
+        words.pop().unwrap()
+    };
+    let options = match context {
+        Context::Command if words.is_empty() => COMMANDS.iter().map(|s| s.to_string()).collect(),
+        Context::Command if words[0] == "bet" => bet_words(&words[1..]),
+        Context::Bet => bet_words(&words),
+        _ => vec![],
+    };
+    let partial = partial.to_lowercase();
+    options.into_iter().filter(|o| o.to_lowercase().starts_with(&partial)).collect()
+}
+
+// returns the words that can follow the input words of a bet
+fn bet_words(words: &[&str]) -> Vec<String> {
+    let ranks: Vec<String> = (2..15).map(|r| Rank::from_u8(r).unwrap().to_string().to_owned()).collect();
+    match (words.first().map(|w| w.to_lowercase()), words.len()) {
+        (None, _) => HAND_TYPES.iter().map(|s| s.to_string()).collect(),
+        (Some(ref kind), 1) if kind == "flush" || kind == "straightflush" => {
+            let mut cards = vec![];
+            for r in 2..15 {
+                for s in 0..4 {
+                    let card = Card {
+                        rank: Rank::from_u8(r).unwrap(),
+                        suit: Suit::from_index(s).unwrap(),
+                    };
+                    cards.push(card.to_text());
+                }
+            }
+            cards
+        }
+        (Some(ref kind), 1) if HAND_TYPES.contains(&kind.as_str()) => ranks,
+        (Some(ref kind), 2) if kind == "twopair" || kind == "fullhouse" => ranks,
+        _ => vec![],
+    }
+}
+
+// Parses a bet typed by the player, e.g. "pair A" or "flush 10h". Explains
+// what is wrong with a bet that is incomplete, unknown or too low, with a
+// legal bet to try instead.
+pub fn parse_bet(text: &str, view: &PlayerView) -> Result<HandValue, String> {
+    let lowest = match view.min_raise() {
+        Some(lowest) => lowest,
+        None => return Err("No bet is higher than the current bet; you can only call".to_owned()),
+    };
+    let words: Vec<String> = text.split_whitespace()
+        .enumerate()
+        .map(|(i, w)| if i == 0 { w.to_lowercase() } else { w.to_uppercase() })
+        .collect();
+    let kind = match words.first() {
+        Some(kind) => kind.as_str(),
+        None => return Err(format!("Enter a bet, e.g. {}", lowest.to_text())),
+    };
+    let (needed, what) = match kind {
+        "twopair" | "fullhouse" => (2, "two ranks"),
+        "flush" | "straightflush" => (1, "a top card"),
+        _ if HAND_TYPES.contains(&kind) => (1, "a rank"),
+        _ => return Err(format!("Unknown hand type {}; use one of {}", kind, HAND_TYPES.join(", "))),
+    };
+    // the lowest legal bet of the same kind of hand
+    let example = HandValue::ladder()
+        .into_iter()
+        .skip(lowest.ladder_index().unwrap())
+        .find(|hv| hv.to_text().split(' ').next() == Some(kind))
+        .unwrap_or(lowest)
+        .to_text();
+    let bet = match HandValue::from_text(&words.join(" ")) {
+        Some(bet) => bet,
+        None if words.len() <= needed => return Err(format!("{} needs {}, e.g. {}", kind, what, example)),
+        None => return Err(format!("{} is not a bet; try {}", words.join(" "), example)),
+    };
+    if !view.legal_moves().contains(&GameMove::Bet(bet)) {
+        return Err(format!("{} does not beat the current bet {}; try {}",
+                           bet.to_text(),
+                           view.current_bet.unwrap().to_text(),
+                           example));
+    }
+    Ok(bet)
+}
+
+// Parses which player at the table to seat and who plays them, e.g.
+// "1 human" or "2 ext:python3 mybot.py". Everything after the player is the
+// seat, so an external bot's command may have arguments.
+pub fn parse_seat(text: &str, view: &PlayerView) -> Result<(u8, Seat), String> {
+    let mut words = text.trim().splitn(2, ' ');
+    let player = words.next().unwrap_or("");
+    let seat = words.next().map(str::trim).unwrap_or("");
+    if player.is_empty() || seat.is_empty() {
+        return Err("Enter a player and a seat, e.g. 1 random".to_owned());
+    }
+    let name = match player.parse() {
+        Ok(name) => name,
+        Err(_) => return Err(format!("Invalid player: {}", player)),
+    };
+    if !view.counts.iter().any(|&(n, _)| n == name) {
+        let names: Vec<String> = view.counts.iter().map(|&(n, _)| n.to_string()).collect();
+        return Err(format!("Player {} is not at the table; seat one of {}",
+                           name,
+                           names.join(", ")));
+    }
+    let seat = if seat == "human" {
+        Seat::Human
+    } else {
+        match seat.parse::<BotKind>() {
+            Ok(kind) => kind.seat(),
+            Err(_) => return Err(format!("Unknown seat: {}", seat)),
+        }
+    };
+    Ok((name, seat))
+}
+
+// Checks a command line: a known command, and for "bet" or "seat" with more
+// after it, a legal bet or a player at the table and a known seat
+pub fn check_command(line: &str, view: &PlayerView) -> Result<(), String> {
+    let mut words = line.splitn(2, ' ');
+    match words.next().unwrap_or("") {
+        "bet" => {
+            match words.next() {
+                Some(bet) if !bet.trim().is_empty() => parse_bet(bet, view).map(|_| ()),
+                _ => Ok(()),
+            }
+        }
+        "seat" => {
+            match words.next() {
+                Some(seat) if !seat.trim().is_empty() => parse_seat(seat, view).map(|_| ()),
+                _ => Ok(()),
+            }
+        }
+        "" => Err(format!("Enter one of {}", COMMANDS.join(", "))),
+        command @ "save" | command @ "load" => {
+            match words.next() {
+                Some(file) if !file.trim().is_empty() => Ok(()),
+                _ => Err(format!("{} needs a file, e.g. {} game.txt", command, command)),
+            }
+        }
+        "call" if view.current_bet.is_none() => Err("There is no bet to call yet".to_owned()),
+        command if COMMANDS.contains(&command) && !line.contains(' ') => Ok(()),
+        _ => Err(format!("Unknown command {}; use one of {}", line, COMMANDS.join(", "))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bullpoker::game::{GameMove, GameState, Rules, Seat};
+    use bullpoker::poker::HandValue;
+    use input::{check_command, completions, parse_bet, parse_seat, Context};
+
+    #[test]
+    fn test_complete_commands_and_bets() {
+        assert_eq!(completions(Context::Command, "").len(), 10);
+        assert_eq!(completions(Context::Command, "b"), ["bet"]);
+        assert_eq!(completions(Context::Command, "bet full"), ["fullhouse"]);
+        assert_eq!(completions(Context::Bet, "straight"), ["straight", "straightflush"]);
+        assert_eq!(completions(Context::Bet, "pair ").len(), 13);
+        assert_eq!(completions(Context::Bet, "twopair k "), completions(Context::Bet, "pair "));
+        assert_eq!(completions(Context::Bet, "flush a"), ["AC", "AD", "AH", "AS"]);
+        assert!(completions(Context::Bet, "pair A ").is_empty());
+        assert!(completions(Context::Plain, "b").is_empty());
+    }
+
+    #[test]
+    fn test_parse_bet_explains_mistakes() {
+        let state = GameState::seeded(2, 3, Rules::default(), 1).unwrap();
+        let state = state.handle_gamemove(GameMove::Bet(HandValue::from_text("pair 5").unwrap()));
+        let view = state.view(state.current_turn);
+        assert_eq!(parse_bet("pair a", &view), Ok(HandValue::from_text("pair A").unwrap()));
+        assert_eq!(parse_bet("flush 10h", &view), Ok(HandValue::from_text("flush 10H").unwrap()));
+        assert_eq!(parse_bet("fullhouse K", &view), Err("fullhouse needs two ranks, e.g. fullhouse 2 3".to_owned()));
+        assert_eq!(parse_bet("pair 3", &view),
+                   Err("pair 3 does not beat the current bet pair 5; try pair 6".to_owned()));
+        assert!(parse_bet("fulhouse K Q", &view).unwrap_err().starts_with("Unknown hand type"));
+        assert!(parse_bet("straight 3", &view).unwrap_err().ends_with("try straight 6"));
+        assert!(parse_bet("", &view).is_err());
+
+        assert!(check_command("bet", &view).is_ok());
+        assert!(check_command("bet triple 2", &view).is_ok());
+        assert!(check_command("bet pair", &view).is_err());
+        assert!(check_command("cal", &view).is_err());
+        assert!(check_command("save", &view).is_err());
+        assert!(check_command("load my game.txt", &view).is_ok());
+        assert!(check_command("call", &view).is_ok());
+    }
+
+    #[test]
+    fn test_parse_seat_keeps_the_bot_command() {
+        let state = GameState::seeded(3, 3, Rules::default(), 1).unwrap();
+        let view = state.view(state.current_turn);
+        assert_eq!(parse_seat("1 human", &view), Ok((1, Seat::Human)));
+        assert_eq!(parse_seat("2 random", &view), Ok((2, Seat::Bot("random".to_owned()))));
+        assert_eq!(parse_seat("1 ext:python3 mybot.py", &view),
+                   Ok((1, Seat::Bot("ext:python3 mybot.py".to_owned()))));
+        assert!(parse_seat("1", &view).is_err());
+        assert!(parse_seat("x random", &view).unwrap_err().starts_with("Invalid player"));
+        assert!(parse_seat("1 robot", &view).unwrap_err().starts_with("Unknown seat"));
+        assert!(parse_seat("9 random", &view).unwrap_err().contains("not at the table"));
+
+        assert!(check_command("seat", &view).is_ok());
+        assert!(check_command("seat 1 ext:python3 mybot.py", &view).is_ok());
+        assert!(check_command("seat 1 robot", &view).is_err());
+        assert!(check_command("seat 3 random", &view).is_err());
+    }
+}
@@ -0,0 +1,21 @@
+extern crate rand;
+
+pub mod arena;
+pub mod card;
+pub mod env;
+pub mod game;
+pub mod inference;
+pub mod logic;
+pub mod nn;
+pub mod poker;
+pub mod replay;
+pub mod reveal;
+pub mod rng;
+pub mod save;
+pub mod selfplay;
+pub mod strategy;
+pub mod table;
+pub mod term;
+pub mod tui;
+pub mod tune;
+pub mod undo;
@@ -1,7 +1,472 @@
+use rand::Rng;
+
+use std::cmp::Ordering;
+
+use card::{Card, Rank};
+use game::{GameMove, PlayerView, Rules};
 use poker::{Hand, HandValue};
+use rng::GameRng;
+
+// A group of cards of which at least `need` must be in play for a handvalue
+// to exist. `size` counts only the cards of the group that are not in your
+// hand, since those are the ones that can still be dealt.
+struct Requirement {
+    size: usize,
+    need: usize,
+}
+
+// Returns the probability that the handvalue exists given your hand and the
+// total amount of cards. `total` includes the cards in your hand; the rest are
+// dealt uniformly from the cards you cannot see.
+pub fn handvalue_prob(hv: &HandValue, hand: &Hand, total: u8) -> f64 {
+    let unseen = 52 - hand.cards.len();
+    let mut drawn = (total as usize).saturating_sub(hand.cards.len());
+    if drawn > unseen {
+        drawn = unseen;
+    }
+    match requirements(hv, hand) {
+        Some(reqs) => prob_requirements_met(&reqs, unseen, drawn),
+        None => 0.0,
+    }
+}
+
+// returns the groups of unseen cards the handvalue needs, or None if the
+// handvalue can no longer exist
+fn requirements(hv: &HandValue, hand: &Hand) -> Option<Vec<Requirement>> {
+    let groups = hv.card_groups()?;
+    let mut output = vec![];
+    for (cards, required) in groups {
+        let held = cards.iter().filter(|c| hand.cards.contains(c)).count();
+        if held >= required {
+            continue;
+        }
+        let req = Requirement {
+            size: cards.len() - held,
+            need: required - held,
+        };
+        if req.need > req.size {
+            return None;
+        }
+        output.push(req);
+    }
+    Some(output)
+}
+
+// Probability that dealing `drawn` of the `unseen` cards meets every
+// requirement. The groups are disjoint, so this sums the multivariate
+// hypergeometric probability over every way of meeting them.
+fn prob_requirements_met(reqs: &[Requirement], unseen: usize, drawn: usize) -> f64 {
+    let in_groups: usize = reqs.iter().map(|r| r.size).sum();
+    let rest = unseen - in_groups;
+    let ways = count_ways(reqs, rest, drawn);
+    ways / choose(unseen, drawn)
+}
+
+// number of ways to draw `drawn` cards meeting every requirement, with `rest`
+// cards outside of the groups
+fn count_ways(reqs: &[Requirement], rest: usize, drawn: usize) -> f64 {
+    match reqs.split_first() {
+        None => choose(rest, drawn),
+        Some((req, others)) => {
+            let mut ways = 0.0;
+            let mut taken = req.need;
+            while taken <= req.size && taken <= drawn {
+                ways += choose(req.size, taken) * count_ways(others, rest, drawn - taken);
+                taken += 1;
+            }
+            ways
+        }
+    }
+}
+
+// binomial coefficient n choose k
+pub fn choose(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    let k = if k > n - k { n - k } else { k };
+    let mut output = 1.0;
+    for i in 0..k {
+        output = output * (n - i) as f64 / (i + 1) as f64;
+    }
+    output
+}
+
+// Returns every legal move for the player to act with its expected change in
+// that player's number of cards, best move first.
+//
+// Calling costs the caller the penalty when the current bet is present. A
+// raise costs the bettor the penalty when the next player calls it and it is
+// missing. The next player is assumed to call a bet when, from their own
+// cards, it looks more likely missing than there. Whether they call and
+// whether the bet is there depend on the same hidden cards, so both are
+// judged over the same sampled hands of their size from the cards you cannot
+// see: the chance that a raise costs you is the average, over the hands that
+// would call it, of the chance it is missing given your cards and theirs.
+pub fn recommend(view: &PlayerView, rules: &Rules) -> Vec<(GameMove, f64)> {
+    recommend_with(view, rules, &mut |hv, hand, total| handvalue_prob(hv, hand, total))
+}
+
+// recommends moves like recommend, taking the probability of a bet from the
+// input function, e.g. a precomputed table
+pub fn recommend_with(view: &PlayerView,
+                      rules: &Rules,
+                      prob: &mut dyn FnMut(&HandValue, &Hand, u8) -> f64)
+                      -> Vec<(GameMove, f64)> {
+    let total = view.total_cards();
+    let delta = rules.loser_delta() as f64;
+    let next_hands = next_player_hands(view);
+    // your cards together with each sampled hand of the next player
+    let joined: Vec<Hand> = next_hands.iter()
+        .map(|hand| {
+            let mut cards = view.hand.clone();
+            cards.add_cards(hand.clone());
+            cards
+        })
+        .collect();
+    let mut output = vec![];
+    if let Some(bet) = view.current_bet {
+        let present = prob(&bet, &view.hand, total);
+        output.push((GameMove::Call(), present * delta));
+    }
+    for hv in HandValue::ladder() {
+        if view.current_bet.is_some_and(|bet| hv <= bet) {
+            continue;
+        }
+        let mut called_missing = 0.0;
+        for (hand, both) in next_hands.iter().zip(&joined) {
+            if prob(&hv, hand, total) < 0.5 {
+                called_missing += 1.0 - prob(&hv, both, total);
+            }
+        }
+        let cost = called_missing / next_hands.len() as f64;
+        output.push((GameMove::Bet(hv), cost * delta));
+    }
+    // either penalty hurts the loser, so the best move has the smallest
+    // expected penalty
+    output.sort_by(|a, b| {
+        (a.1 * delta).partial_cmp(&(b.1 * delta)).unwrap_or(Ordering::Equal)
+    });
+    output
+}
+
+// Hands of the next player sampled by recommend. Seeded so that the same
+// position always gets the same recommendation.
+const NEXT_HAND_SAMPLES: usize = 32;
+const NEXT_HAND_SEED: u64 = 0;
+
+// returns hands the player after the viewer could hold: their number of cards
+// drawn from the cards the viewer cannot see
+fn next_player_hands(view: &PlayerView) -> Vec<Hand> {
+    let at = view.counts.iter().position(|&(name, _)| name == view.player).unwrap_or(0);
+    let size = view.counts.get((at + 1) % view.counts.len().max(1)).map_or(0, |&(_, count)| count);
+    let mut rng = GameRng::from_seed(NEXT_HAND_SEED);
+    let mut unseen: Vec<Card> = standard_deck()
+        .into_iter()
+        .filter(|c| !view.hand.cards.contains(c))
+        .collect();
+    let size = size.min(unseen.len());
+    (0..NEXT_HAND_SAMPLES)
+        .map(|_| {
+            // partial Fisher-Yates shuffle of the first `size` cards
+            for i in 0..size {
+                let j = rng.gen_range(i, unseen.len());
+                unseen.swap(i, j);
+            }
+            Hand { cards: unseen[..size].to_vec() }
+        })
+        .collect()
+}
+
+// Settings for estimating a handvalue probability by sampling
+#[derive(Clone, Copy, Debug)]
+pub struct SampleConfig {
+    pub seed: u64,
+    // never deal more samples than this
+    pub max_samples: usize,
+    // stop early once the confidence interval is at most this wide on each
+    // side of the estimate
+    pub precision: Option<f64>,
+    // z score of the confidence interval, 1.96 for 95%
+    pub z: f64,
+}
+
+impl Default for SampleConfig {
+    fn default() -> SampleConfig {
+        SampleConfig {
+            seed: 0,
+            max_samples: 100_000,
+            precision: Some(0.005),
+            z: 1.96,
+        }
+    }
+}
+
+// A sampled probability with its confidence interval
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub prob: f64,
+    pub low: f64,
+    pub high: f64,
+    pub samples: usize,
+}
+
+// Samples are checked against the target precision in batches of this size
+const SAMPLE_BATCH: usize = 500;
+
+// Estimates the probability that the handvalue exists given your hand and the
+// total amount of cards by dealing the unseen cards at random
+pub fn sample_handvalue_prob(hv: &HandValue, hand: &Hand, total: u8, config: &SampleConfig)
+    -> Estimate {
+    let unseen: Vec<Card> = standard_deck()
+        .into_iter()
+        .filter(|c| !hand.cards.contains(c))
+        .collect();
+    let drawn = (total as usize).saturating_sub(hand.cards.len());
+    sample_prob_from(hv, hand, &unseen, drawn, config)
+}
+
+// Estimates the probability that the handvalue exists when `drawn` cards from
+// `unseen` join your hand. Taking the unseen cards as input lets variants with
+// other decks (jokers, several decks) reuse the estimator.
+pub fn sample_prob_from(hv: &HandValue, hand: &Hand, unseen: &[Card], drawn: usize,
+                        config: &SampleConfig) -> Estimate {
+    let mut rng = GameRng::from_seed(config.seed);
+    let mut deck = unseen.to_vec();
+    let drawn = if drawn > deck.len() { deck.len() } else { drawn };
+    let mut pool = hand.clone();
+    let mut hits = 0;
+    let mut samples = 0;
+    while samples < config.max_samples {
+        // partial Fisher-Yates shuffle of the first `drawn` cards
+        pool.cards.truncate(hand.cards.len());
+        for i in 0..drawn {
+            let j = rng.gen_range(i, deck.len());
+            deck.swap(i, j);
+            pool.cards.push(deck[i]);
+        }
+        if pool.contains_handvalue(hv) {
+            hits += 1;
+        }
+        samples += 1;
+        if samples % SAMPLE_BATCH == 0 {
+            if let Some(precision) = config.precision {
+                let est = wilson(hits, samples, config.z);
+                if (est.high - est.low) / 2.0 <= precision {
+                    return est;
+                }
+            }
+        }
+    }
+    wilson(hits, samples, config.z)
+}
+
+// Wilson score interval, which stays inside [0, 1] for probabilities near the
+// edges where most bets live
+pub fn wilson(hits: usize, samples: usize, z: f64) -> Estimate {
+    if samples == 0 {
+        return Estimate {
+            prob: 0.0,
+            low: 0.0,
+            high: 1.0,
+            samples: 0,
+        };
+    }
+    let n = samples as f64;
+    let p = hits as f64 / n;
+    let z2 = z * z;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    Estimate {
+        prob: p,
+        low: (center - half).max(0.0),
+        high: (center + half).min(1.0),
+        samples,
+    }
+}
+
+// returns the 52 cards in a fixed order
+fn standard_deck() -> Vec<Card> {
+    let mut cards = vec![];
+    for rank in 2..15 {
+        cards.extend_from_slice(&Card::get_all_with_rank(Rank::from_u8(rank).unwrap()));
+    }
+    cards
+}
+
+#[cfg(test)]
+mod test {
+    use game::{GameMove, Penalty, PlayerView, Rules};
+    use logic::{handvalue_prob, recommend, sample_handvalue_prob, SampleConfig,
+                NEXT_HAND_SAMPLES};
+    use card::{Card, Suit, Rank};
+    use poker::{Hand, HandValue};
+
+    // exact probability by dealing every possible set of unseen cards
+    fn brute_force(hvs: &[HandValue], hand: &Hand, total: u8) -> Vec<f64> {
+        let unseen: Vec<Card> = Hand::get_full_deck()
+            .cards
+            .into_iter()
+            .filter(|c| !hand.cards.contains(c))
+            .collect();
+        let drawn = total as usize - hand.cards.len();
+        let mut hits = vec![0u64; hvs.len()];
+        let mut deals = 0u64;
+        let mut indices: Vec<usize> = (0..drawn).collect();
+        loop {
+            let mut pool = hand.clone();
+            for &i in &indices {
+                pool.cards.push(unseen[i]);
+            }
+            for (j, hv) in hvs.iter().enumerate() {
+                if pool.contains_handvalue(hv) {
+                    hits[j] += 1;
+                }
+            }
+            deals += 1;
+            // advance to the next combination of indices
+            let mut i = drawn;
+            while i > 0 && indices[i - 1] == unseen.len() - drawn + i - 1 {
+                i -= 1;
+            }
+            if i == 0 {
+                break;
+            }
+            indices[i - 1] += 1;
+            for j in i..drawn {
+                indices[j] = indices[j - 1] + 1;
+            }
+        }
+        hits.iter().map(|&h| h as f64 / deals as f64).collect()
+    }
+
+    fn check_against_brute_force(hand: Hand, total: u8) {
+        let ladder = HandValue::ladder();
+        let expected = brute_force(&ladder, &hand, total);
+        for (hv, p) in ladder.iter().zip(expected) {
+            let exact = handvalue_prob(hv, &hand, total);
+            assert!((exact - p).abs() < 1e-9, "{:?}: {} != {}", hv, exact, p);
+        }
+    }
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card {
+            rank: rank,
+            suit: suit,
+        }
+    }
+
+    #[test]
+    fn test_prob_matches_brute_force_pairs() {
+        let mut cards = vec![];
+        cards.push(card(Rank::Queen, Suit::Hearts));
+        cards.push(card(Rank::Queen, Suit::Spades));
+        cards.push(card(Rank::Five, Suit::Clubs));
+        check_against_brute_force(Hand { cards: cards }, 5);
+    }
+
+    #[test]
+    fn test_prob_matches_brute_force_straights() {
+        let mut cards = vec![];
+        cards.push(card(Rank::Seven, Suit::Hearts));
+        cards.push(card(Rank::Eight, Suit::Hearts));
+        cards.push(card(Rank::Nine, Suit::Hearts));
+        cards.push(card(Rank::Ten, Suit::Hearts));
+        check_against_brute_force(Hand { cards: cards }, 6);
+    }
+
+    #[test]
+    fn test_prob_matches_brute_force_three_drawn() {
+        let mut cards = vec![];
+        cards.push(card(Rank::Two, Suit::Diamonds));
+        cards.push(card(Rank::Three, Suit::Diamonds));
+        cards.push(card(Rank::Four, Suit::Diamonds));
+        cards.push(card(Rank::Five, Suit::Diamonds));
+        check_against_brute_force(Hand { cards: cards }, 7);
+    }
+
+    #[test]
+    fn test_prob_edge_cases() {
+        let hand = Hand { cards: vec![card(Rank::Ace, Suit::Spades)] };
+        let ace = HandValue::HighCard(Rank::Ace);
+        let king = HandValue::HighCard(Rank::King);
+        assert_eq!(handvalue_prob(&ace, &hand, 1), 1.0);
+        assert_eq!(handvalue_prob(&king, &hand, 1), 0.0);
+        assert_eq!(handvalue_prob(&king, &hand, 52), 1.0);
+        assert_eq!(handvalue_prob(&HandValue::Straight(Rank::Five), &hand, 52), 0.0);
+    }
+
+    #[test]
+    fn test_sample_prob_within_interval() {
+        let mut cards = vec![];
+        cards.push(card(Rank::Queen, Suit::Hearts));
+        cards.push(card(Rank::Nine, Suit::Clubs));
+        let hand = Hand { cards: cards };
+        let config = SampleConfig {
+            seed: 7,
+            precision: None,
+            max_samples: 20_000,
+            ..SampleConfig::default()
+        };
+        for hv in &[HandValue::OnePair(Rank::Queen), HandValue::Straight(Rank::Jack)] {
+            let exact = handvalue_prob(hv, &hand, 12);
+            let est = sample_handvalue_prob(hv, &hand, 12, &config);
+            assert_eq!(est.samples, 20_000);
+            assert!(est.low <= exact && exact <= est.high, "{:?}: {:?} vs {}", hv, est, exact);
+            // the same seed deals the same samples
+            assert_eq!(est, sample_handvalue_prob(hv, &hand, 12, &config));
+        }
+    }
+
+    #[test]
+    fn test_sample_prob_stops_early() {
+        let hand = Hand { cards: vec![card(Rank::Ace, Suit::Spades)] };
+        let config = SampleConfig {
+            precision: Some(0.02),
+            ..SampleConfig::default()
+        };
+        let est = sample_handvalue_prob(&HandValue::OnePair(Rank::Ace), &hand, 10, &config);
+        assert!(est.samples < config.max_samples);
+        assert!((est.high - est.low) / 2.0 <= 0.02);
+    }
+
+    #[test]
+    fn test_recommend_calls_impossible_bet() {
+        let view = PlayerView {
+            player: 0,
+            hand: Hand { cards: vec![card(Rank::Ace, Suit::Spades)] },
+            counts: vec![(0, 1), (1, 1)],
+            current_turn: 0,
+            current_bet: Some(HandValue::FourOfAKind(Rank::King)),
+            history: vec![],
+        };
+        let mut rules = Rules::default();
+        let moves = recommend(&view, &rules);
+        assert_eq!(moves[0], (GameMove::Call(), 0.0));
+        // the call, four aces and the straight flushes
+        assert_eq!(moves.len(), 1 + 1 + 36);
+        rules.penalty = Penalty::GainCard;
+        let moves = recommend(&view, &rules);
+        assert_eq!(moves[0], (GameMove::Call(), 0.0));
+        assert!(moves.iter().all(|&(_, ev)| ev >= 0.0));
+    }
 
-// Returns the probability that the handvalue exists given your hand and the 
-// total amount of cards
-fn handvalue_prob(hv: &HandValue, hand: &Hand, total: u8) -> f64 {
-    unimplemented!()
+    #[test]
+    fn test_recommend_judges_call_and_bet_together() {
+        let view = PlayerView {
+            player: 0,
+            hand: Hand { cards: vec![card(Rank::Ace, Suit::Spades)] },
+            counts: vec![(0, 1), (1, 1)],
+            current_turn: 0,
+            current_bet: None,
+            history: vec![],
+        };
+        let moves = recommend(&view, &Rules::default());
+        let pair = GameMove::Bet(HandValue::OnePair(Rank::Ace));
+        let ev = moves.iter().find(|&&(gm, _)| gm == pair).unwrap().1;
+        // the next player calls pair A exactly when they hold no ace, and
+        // then it is certainly missing, so every call costs a whole card
+        let calls = -ev * NEXT_HAND_SAMPLES as f64;
+        assert!((calls - calls.round()).abs() < 1e-9 && calls > 0.0, "{}", ev);
+    }
 }
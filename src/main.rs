@@ -1,113 +1,594 @@
-extern crate rand;
+extern crate bullpoker;
 
-mod card;
-mod game;
-mod logic;
-mod poker;
+mod cli;
+mod hotseat;
+mod input;
 
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::env;
+use std::fs::File;
 use std::io;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
 
-use card::{Card, Suit, Rank};
-use game::{Player, GameMove, GameState};
-use poker::{Hand, HandValue, BPFlush, BPStraightFlush};
+use bullpoker::card::RenderOptions;
+use bullpoker::game::{GameMove, GameState, PlayerView, Seat};
+use bullpoker::logic;
+use bullpoker::nn::{self, TrainConfig};
+use bullpoker::poker::{Hand, HandValue};
+use bullpoker::replay::Replay;
+use bullpoker::reveal::Reveal;
+use bullpoker::rng::GameRng;
+use bullpoker::save;
+use bullpoker::selfplay::{self, SelfPlayConfig};
+use bullpoker::strategy::{BotKind, Strategy};
+use bullpoker::strategy::cfr::{CfrConfig, CfrSolver};
+use bullpoker::table::{LazyTable, ProbTable};
+use bullpoker::tui;
+use bullpoker::tune::{self, Family, TuneConfig};
+use bullpoker::undo::UndoStack;
+use cli::Options;
+use input::{Context, Editor};
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "table" {
+        generate_table(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "cfr" {
+        solve_cfr(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "train" {
+        train_network(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "tune" {
+        tune_bot(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "replay" {
+        replay_game(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "selfplay" {
+        generate_selfplay(&args[2..]);
+        return;
+    }
+    let options = match Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, cli::USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if options.tui {
+        if let Err(error) = tui::run(options.start(), options.render) {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }
+        return;
+    }
     println!("Welcome to BullPoker!");
-    let mut state = GameState::init_game(1, 0).unwrap();
+    let mut state = options.start();
+    if !options.hotseat {
+        state.display(&options.render_options());
+    }
+    // computer players, built the first time they have to move
+    let mut bots: HashMap<u8, Box<dyn Strategy>> = HashMap::new();
+    // in hot-seat mode, the player whose hand is on screen and the length of
+    // the history when it was shown
+    let mut shown = None;
+    let mut editor = Editor::new();
+    let mut moves = UndoStack::new();
+    // probabilities for hints, read from the table the first time one is needed
+    let mut table = options.table.as_ref().map(LazyTable::new);
     loop {
+        if state.players.len() > 1 {
+            let player = state.players[state.current_turn].clone();
+            if let Seat::Bot(ref name) = player.seat {
+                let shown = state.player_name(player.name);
+                let bot = match bots.entry(player.name) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        match BotKind::build_seat(name, GameRng::random_seed()) {
+                            Ok(bot) => entry.insert(bot),
+                            Err(error) => {
+                                // a human takes over until the seat is changed
+                                println!("{} cannot play: {}; seated a human instead",
+                                         shown,
+                                         error);
+                                state.set_seat(player.name, Seat::Human);
+                                continue;
+                            }
+                        }
+                    }
+                };
+                let gm = bot.choose(&state.view(state.current_turn), &state.rules);
+                match gm {
+                    GameMove::Bet(hv) => println!("{} ({}) bets {:?}", shown, bot.name(), hv),
+                    GameMove::Call() => println!("{} ({}) calls", shown, bot.name()),
+                    GameMove::NewGame(..) => {}
+                }
+                let calls = hotseat::calls(&state);
+                moves.play(&mut state, gm);
+                if !show_move(&state, &options, calls) {
+                    break;
+                }
+                continue;
+            }
+        }
+        let turn = (state.players[state.current_turn].name, state.history.len());
+        if options.hotseat && shown != Some(turn) {
+            if !hotseat::pass_to(&state) {
+                break;
+            }
+            hotseat::show_turn(&state, &options.render_options());
+            shown = Some(turn);
+        }
         println!("Current Bet: {:?}", state.current_bet);
         println!(
-            "Player {} - What is your next move? (new, bet, call)",
-            state.players[state.current_turn].name
+            "{} - What is your next move? (new, seat, bet, call, hint, undo, redo, save, load)",
+            state.player_name(state.players[state.current_turn].name)
         );
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(n) => {
-                match input.trim() {
-                    "new" => {
-                        let (players, cards) = parse_players_cards();
-                        state = state.handle_gamemove(GameMove::NewGame(players, cards));
-                    }
-                    "bet" => {
-                        let handvalue = parse_handvalue();
-                        state = state.handle_gamemove(GameMove::Bet(handvalue));
-                    }
-                    "call" => {
-                        state = state.handle_gamemove(GameMove::Call());
-                    }
-                    "quit" => break,
-                    _ => println!("Invalid input!"),
+        let view = state.view(state.current_turn);
+        let check = |line: &str| input::check_command(line, &view);
+        let line = match editor.read_line("> ", Context::Command, &check) {
+            Some(line) => line,
+            None => break,
+        };
+        let calls = hotseat::calls(&state);
+        let mut words = line.splitn(2, ' ');
+        match words.next().unwrap() {
+            "new" => {
+                // the new game keeps the rules, names and seats of the options
+                state = match parse_players_cards(&mut editor, &options) {
+                    Some(new) => new.start(),
+                    None => continue,
                 };
-                state.display();
-            }
-            Err(error) => println!("error: {}", error),
-        }
-    }
-}
-
-fn parse_handvalue() -> HandValue {
-    println!("Enter Your Bet (e.g. quad A):");
-    let mut input = String::new();
-    match io::stdin().read_line(&mut input) {
-        Ok(_) => {
-            let vec: Vec<&str> = input.split(" ").collect();
-            let mut rank_one = None;
-            let mut rank_two = None;
-            let mut card = None;
-            let hand = vec[0].trim();
-            if vec[1].trim().len() == 1 {
-                rank_one = Rank::from_str(vec[1].trim());
-            } else {
-                let ch = vec[1].chars().nth(0).unwrap();
-                let suit = match vec[1].chars().nth(1).unwrap() {
-                    'C' => Suit::Clubs,
-                    'D' => Suit::Diamonds,
-                    'H' => Suit::Hearts,
-                    'S' => Suit::Spades,
-                    _ => panic!()
+                bots.clear();
+                moves.clear();
+            }
+            "seat" => {
+                // the seat may follow the command, e.g. "seat 1 random"
+                let seat = match words.next() {
+                    Some(text) => input::parse_seat(text, &view).ok(),
+                    None => parse_seat(&mut editor, &view),
+                };
+                if let Some((name, seat)) = seat {
+                    state.set_seat(name, seat);
+                    bots.remove(&name);
+                }
+                continue;
+            }
+            "bet" => {
+                // the bet may follow the command, e.g. "bet pair A"
+                let bet = match words.next() {
+                    Some(text) => input::parse_bet(text, &view).ok(),
+                    None => parse_handvalue(&mut editor, &view),
                 };
-                card = Some(Card {
-                    rank: Rank::from_str(&ch.to_string()).unwrap(),
-                    suit: suit
-                });
-            }
-            if vec.len() == 3 {
-                rank_two = Rank::from_str(vec[2].trim());
-            }
-            let handvalue = match hand {
-                "high" => HandValue::HighCard(rank_one.unwrap()),
-                "pair" => HandValue::OnePair(rank_one.unwrap()),
-                "twopair" => HandValue::TwoPair(rank_one.unwrap(), rank_two.unwrap()),
-                "triple" => HandValue::ThreeOfAKind(rank_one.unwrap()),
-                "straight" => HandValue::Straight(rank_one.unwrap()),
-                "flush" => HandValue::Flush(BPFlush { card: card.unwrap() }),
-                "fullhouse" => HandValue::FullHouse(rank_one.unwrap(), rank_two.unwrap()),
-                "quad" => HandValue::FourOfAKind(rank_one.unwrap()),
-                "straightflush" => HandValue::StraightFlush(BPStraightFlush { card: card.unwrap() }),
-                _ => panic!(),
-            };
-            handvalue
+                match bet {
+                    Some(bet) => moves.play(&mut state, GameMove::Bet(bet)),
+                    None => continue,
+                }
+            }
+            "call" => moves.play(&mut state, GameMove::Call()),
+            "undo" => {
+                if let Err(error) = undo_move(&mut moves, &mut state) {
+                    println!("{}", error);
+                    continue;
+                }
+            }
+            "save" => {
+                let file = words.next().unwrap().trim();
+                match save::save_game(&state, Path::new(file)) {
+                    Ok(()) => println!("Saved the game to {}", file),
+                    Err(error) => println!("error: {}", error),
+                }
+                continue;
+            }
+            "load" => {
+                match save::load_game(Path::new(words.next().unwrap().trim())) {
+                    Ok(loaded) => {
+                        state = loaded;
+                        bots.clear();
+                        moves.clear();
+                        if !options.hotseat {
+                            state.display(&options.render_options());
+                        }
+                    }
+                    Err(error) => println!("{}", error),
+                }
+                continue;
+            }
+            "redo" => {
+                if let Err(error) = redo_move(&mut moves, &mut state) {
+                    println!("{}", error);
+                    continue;
+                }
+            }
+            "hint" => {
+                print_hint(&state, &mut table);
+                continue;
+            }
+            _ => break,
+        };
+        if !show_move(&state, &options, calls) {
+            break;
         }
-        Err(error) => {
-            println!("error: {}", error);
-            panic!()
+    }
+}
+
+// Shows the table after a move: what a call revealed, and every hand unless
+// in hot-seat mode. Returns false when the input is closed.
+fn show_move(state: &GameState, options: &Options, calls: usize) -> bool {
+    let called = hotseat::calls(state) > calls;
+    let cards = options.render_options();
+    if options.hotseat {
+        if called && !hotseat::show_reveal(state, &cards) {
+            return false;
+        }
+    } else {
+        if called {
+            if let Some(reveal) = Reveal::last(state) {
+                println!("{}", reveal.report(state, &cards));
+            }
         }
+        state.display(&cards);
+    }
+    if let Some(winner) = state.winner() {
+        println!("{} wins!", state.player_name(winner));
     }
+    true
+}
+
+// Takes back moves up to the last one made by a human, as bots would just
+// make theirs again. Fails if only bot moves can be taken back.
+fn undo_move(moves: &mut UndoStack, state: &mut GameState) -> Result<(), String> {
+    moves.undo(state)?;
+    let mut undone = 1;
+    while state.players[state.current_turn].seat != Seat::Human {
+        if moves.undo(state).is_err() {
+            for _ in 0..undone {
+                moves.redo(state)?;
+            }
+            return Err("There is no move of yours to undo".to_owned());
+        }
+        undone += 1;
+    }
+    Ok(())
+}
+
+// makes the undone moves again up to the next move of a human
+fn redo_move(moves: &mut UndoStack, state: &mut GameState) -> Result<(), String> {
+    moves.redo(state)?;
+    while state.players[state.current_turn].seat != Seat::Human && moves.redo(state).is_ok() {}
+    Ok(())
+}
+
+// reads which player to seat and whether a human or which bot plays them
+fn parse_seat(editor: &mut Editor, view: &PlayerView) -> Option<(u8, Seat)> {
+    println!("Enter player & seat (e.g. 1 random; human, random, call, minraise, easy, medium, hard, bluff, cfr, ismcts, nn:<file>, ext:<command>):");
+    let check = |line: &str| input::parse_seat(line, view).map(|_| ());
+    let line = editor.read_line("> ", Context::Plain, &check)?;
+    input::parse_seat(&line, view).ok()
+}
+
+// prints the best moves for the current player and why, looking the
+// probabilities up in the table if there is one
+fn print_hint(state: &GameState, table: &mut Option<LazyTable>) {
+    let view = state.view(state.current_turn);
+    let total = view.total_cards();
+    let mut prob = |hv: &HandValue, hand: &Hand, total: u8| match *table {
+        Some(ref mut table) => table.prob(hv, hand, total),
+        None => logic::handvalue_prob(hv, hand, total),
+    };
+    let moves = logic::recommend_with(&view, &state.rules, &mut prob);
+    for &(gm, ev) in moves.iter().take(5) {
+        // avoid printing -0.00 for safe moves
+        let ev = if ev == 0.0 { 0.0 } else { ev };
+        match gm {
+            GameMove::Call() => {
+                let bet = view.current_bet.unwrap();
+                println!(
+                    "call: {:+.2} cards ({:?} is there {:.0}% of the time)",
+                    ev,
+                    bet,
+                    100.0 * prob(&bet, &view.hand, total)
+                );
+            }
+            GameMove::Bet(hv) => {
+                println!(
+                    "bet {:?}: {:+.2} cards (there {:.0}% of the time)",
+                    hv,
+                    ev,
+                    100.0 * prob(&hv, &view.hand, total)
+                );
+            }
+            GameMove::NewGame(..) => {}
+        }
+    }
+}
+
+// reads a bet, asking again until it is legal. Returns None if the player
+// enters nothing, to go back to the commands.
+fn parse_handvalue(editor: &mut Editor, view: &PlayerView) -> Option<HandValue> {
+    println!("Enter Your Bet (e.g. quad A), or nothing to go back:");
+    let check = |line: &str| {
+        if line.is_empty() {
+            return Ok(());
+        }
+        input::parse_bet(line, view).map(|_| ())
+    };
+    let line = editor.read_line("> ", Context::Bet, &check)?;
+    input::parse_bet(&line, view).ok()
 }
 
-fn parse_players_cards() -> (u8, u8) {
+fn parse_players_cards(editor: &mut Editor, options: &Options) -> Option<Options> {
     println!("Enter # players & # cards per hand (e.g. 5 6): ");
-    let mut input = String::new();
-    match io::stdin().read_line(&mut input) {
-        Ok(n) => {
-            let vec: Vec<&str> = input.split(" ").collect();
-            let players = vec[0].parse().unwrap();
-            let cards = vec[1].trim().parse().unwrap();
-            (players, cards)
+    let check = |line: &str| players_cards_from(line, options).map(|_| ());
+    let line = editor.read_line("> ", Context::Plain, &check)?;
+    players_cards_from(&line, options).ok()
+}
+
+fn players_cards_from(line: &str, options: &Options) -> Result<Options, String> {
+    let vec: Vec<&str> = line.split_whitespace().collect();
+    let numbers: Vec<u8> = vec.iter().filter_map(|s| s.parse().ok()).collect();
+    if vec.len() != 2 || numbers.len() != 2 {
+        return Err("Enter two numbers, e.g. 5 6".to_owned());
+    }
+    options.with_table(numbers[0], numbers[1])
+}
+
+// bullpoker table <file> [hand size] [max total]
+// precomputes the probability of every bet for every hand of the input size
+fn generate_table(args: &[String]) {
+    if args.is_empty() {
+        println!("usage: bullpoker table <file> [hand size] [max total]");
+        return;
+    }
+    let numbers: Result<Vec<u8>, _> = args[1..].iter().map(|s| s.parse()).collect();
+    let (hand_size, max_total) = match numbers.as_ref().map(|n| n.as_slice()) {
+        Ok([]) => (1, 52),
+        Ok(&[hand_size]) => (hand_size, 52),
+        Ok(&[hand_size, max_total]) => (hand_size, max_total),
+        _ => {
+            println!("usage: bullpoker table <file> [hand size] [max total]");
+            return;
+        }
+    };
+    if hand_size < 1 || max_total > 52 || hand_size > max_total {
+        println!("The hand size must be at least 1 and at most the max total, which is at most 52");
+        return;
+    }
+    let hands = ProbTable::all_hands(hand_size as usize);
+    let totals: Vec<u8> = (hand_size..max_total + 1).collect();
+    let table = ProbTable::generate(&hands, &totals);
+    match table.save(Path::new(&args[0])) {
+        Ok(()) => println!("Wrote {} rows to {}", table.len(), args[0]),
+        Err(error) => println!("error: {}", error),
+    }
+}
+
+// bullpoker cfr [hand size] [iterations] [file]
+// solves the two player game and writes the strategy to the file, or stdout
+fn solve_cfr(args: &[String]) {
+    let mut hand_size = 1;
+    let mut iterations: usize = 1000;
+    if !(read_arg(args, 0, "hand size", &mut hand_size) &&
+         read_arg(args, 1, "iterations", &mut iterations)) {
+        return;
+    }
+    if hand_size != 1 && hand_size != 2 {
+        println!("usage: bullpoker cfr [hand size (1 or 2)] [iterations] [file]");
+        return;
+    }
+    let mut solver = CfrSolver::new(CfrConfig { hand_size, ..CfrConfig::default() });
+    for i in 1..iterations + 1 {
+        solver.iterate();
+        if i % 100 == 0 || i == iterations {
+            println!("iteration {}: exploitability {:.5}", i, solver.exploitability());
+        }
+    }
+    let result = match args.get(2) {
+        Some(path) => File::create(path).and_then(|file| {
+            let mut out = io::BufWriter::new(file);
+            solver.write_strategy(&mut out)
+        }),
+        None => solver.write_strategy(&mut io::stdout()),
+    };
+    if let Err(error) = result {
+        println!("error: {}", error);
+    }
+}
+
+// bullpoker replay <file> [player]
+// steps through a saved game, showing every hand or the player's view
+fn replay_game(args: &[String]) {
+    if args.is_empty() {
+        println!("usage: bullpoker replay <file> [player]");
+        return;
+    }
+    let mut viewer: Option<u8> = None;
+    if let Some(player) = args.get(1) {
+        match cli::number("player", player) {
+            Ok(player) => viewer = Some(player),
+            Err(error) => {
+                println!("{}", error);
+                return;
+            }
         }
+    }
+    let game = match save::load_game(Path::new(&args[0])) {
+        Ok(game) => game,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    let replay = Replay::new(game);
+    let mut step = 0;
+    let mut editor = Editor::new();
+    loop {
+        println!("{}", replay.render(step, viewer, &RenderOptions::default()));
+        println!("n: next move, p: previous, <number>: go to move, v <player>|all: change view, q: quit");
+        let check = |line: &str| {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] | ["n"] | ["p"] | ["q"] | ["v", "all"] => Ok(()),
+                ["v", player] if player.parse::<u8>().is_ok() => Ok(()),
+                [number] if number.parse::<usize>().is_ok() => Ok(()),
+                _ => Err(format!("Unknown command: {}", line)),
+            }
+        };
+        let line = match editor.read_line("> ", Context::Plain, &check) {
+            Some(line) => line,
+            None => break,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] | ["n"] => step = (step + 1).min(replay.len()),
+            ["p"] => step = step.saturating_sub(1),
+            ["q"] => break,
+            ["v", "all"] => viewer = None,
+            ["v", player] => viewer = player.parse().ok(),
+            [number] => step = number.parse::<usize>().unwrap().min(replay.len()),
+            _ => {}
+        }
+    }
+}
+
+// bullpoker selfplay <dir> [shards] [games per shard] [seed] [bot,bot,...]
+// writes every decision of bot games as JSON lines, one file per shard.
+// Shards already in the directory are kept, so a stopped run can be resumed.
+fn generate_selfplay(args: &[String]) {
+    if args.is_empty() {
+        println!("usage: bullpoker selfplay <dir> [shards] [games per shard] [seed] [bot,bot,...]");
+        return;
+    }
+    let mut config = SelfPlayConfig::default();
+    if !(read_arg(args, 1, "shards", &mut config.shards) &&
+         read_arg(args, 2, "games per shard", &mut config.games_per_shard) &&
+         read_arg(args, 3, "seed", &mut config.seed)) {
+        return;
+    }
+    if let Some(bots) = args.get(4) {
+        let kinds: Result<Vec<BotKind>, String> = bots.split(',').map(str::parse).collect();
+        match kinds {
+            Ok(ref kinds) if kinds.len() >= 2 => config.bots = kinds.clone(),
+            _ => {
+                println!("Need at least two known bots, e.g. medium,bluff");
+                return;
+            }
+        }
+    }
+    match selfplay::generate(&config, Path::new(&args[0])) {
+        Ok(written) => println!("Wrote {} of {} shards to {}", written, config.shards, args[0]),
+        Err(error) => println!("error: {}", error),
+    }
+}
+
+// bullpoker train <file> [games] [seed] [players] [cards]
+// trains a network bot by self-play and saves its weights to the file
+fn train_network(args: &[String]) {
+    if args.is_empty() {
+        println!("usage: bullpoker train <file> [games] [seed] [players] [cards]");
+        return;
+    }
+    let mut config = TrainConfig::default();
+    if !(read_arg(args, 1, "games", &mut config.games) &&
+         read_arg(args, 2, "seed", &mut config.seed) &&
+         read_arg(args, 3, "players", &mut config.players) &&
+         read_arg(args, 4, "cards", &mut config.cards)) {
+        return;
+    }
+    if config.players < 2 || config.players as usize * config.cards as usize > 52 {
+        println!("The hands of {} players of {} cards do not fit in the deck",
+                 config.players,
+                 config.cards);
+        return;
+    }
+    let mut recent = 0.0;
+    let net = nn::train(&config, &mut |game, error| {
+        recent += error;
+        if (game + 1) % 1000 == 0 {
+            println!("game {}: value error {:.4}", game + 1, recent / 1000.0);
+            recent = 0.0;
+        }
+    });
+    match net.save(Path::new(&args[0])) {
+        Ok(()) => println!("Saved weights to {}", args[0]),
+        Err(error) => println!("error: {}", error),
+    }
+}
+
+// bullpoker tune <threshold|bluff> <file> [generations] [population] [seed] [bot,bot,...]
+// evolves the parameters of a bot against a panel of opponents and saves the
+// best bot to the file
+fn tune_bot(args: &[String]) {
+    let family = args.first().and_then(|s| s.parse::<Family>().ok());
+    let (family, path) = match (family, args.get(1)) {
+        (Some(family), Some(path)) => (family, path),
+        _ => {
+            println!("usage: bullpoker tune <threshold|bluff> <file> [generations] [population] \
+                      [seed] [bot,bot,...]");
+            return;
+        }
+    };
+    let mut config = TuneConfig {
+        family,
+        ..TuneConfig::default()
+    };
+    if !(read_arg(args, 2, "generations", &mut config.generations) &&
+         read_arg(args, 3, "population", &mut config.population) &&
+         read_arg(args, 4, "seed", &mut config.seed)) {
+        return;
+    }
+    if let Some(panel) = args.get(5) {
+        match panel.split(',').map(str::parse).collect() {
+            Ok(panel) => config.panel = panel,
+            Err(_) => {
+                println!("Unknown bot in the panel: {}", panel);
+                return;
+            }
+        }
+    }
+    let best = tune::evolve(&config, &mut |generation, best| {
+        println!("generation {}: best {} wins {:.1}%",
+                 generation + 1,
+                 family.bot(&best.params),
+                 100.0 * best.fitness);
+    });
+    let best = match best {
+        Ok(best) => best,
         Err(error) => {
             println!("error: {}", error);
-            panic!()
+            return;
+        }
+    };
+    match tune::save_best(&config, &best, Path::new(path)) {
+        Ok(()) => println!("Saved the best bot to {}", path),
+        Err(error) => println!("error: {}", error),
+    }
+}
+
+// Sets the value to the optional argument at the index, if it is given.
+// Returns false, after saying why, if the argument is not a valid number.
+fn read_arg<T: FromStr>(args: &[String], index: usize, name: &str, value: &mut T) -> bool {
+    match args.get(index).map(|arg| cli::number(name, arg)) {
+        Some(Ok(number)) => *value = number,
+        Some(Err(error)) => {
+            println!("{}", error);
+            return false;
         }
+        None => {}
     }
+    true
 }
@@ -0,0 +1,440 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use rand::Rng;
+
+use env::{self, ACTIONS};
+use game::{GameMove, GameState, PlayerView, Rules};
+use rng::GameRng;
+use strategy::Strategy;
+
+// Weight files start with this line
+pub const WEIGHTS_HEADER: &str = "bullpoker-mlp 1";
+
+// A fully connected layer computing weights * input + biases
+#[derive(Clone, Debug, PartialEq)]
+struct Layer {
+    inputs: usize,
+    outputs: usize,
+    // row major, one row per output
+    weights: Vec<f32>,
+    biases: Vec<f32>,
+}
+
+impl Layer {
+    // Glorot uniform initialization
+    fn new(inputs: usize, outputs: usize, rng: &mut GameRng) -> Layer {
+        let limit = (6.0 / (inputs + outputs) as f64).sqrt();
+        Layer {
+            inputs,
+            outputs,
+            weights: (0..inputs * outputs)
+                .map(|_| ((rng.next_f64() * 2.0 - 1.0) * limit) as f32)
+                .collect(),
+            biases: vec![0.0; outputs],
+        }
+    }
+
+    fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let mut output = self.biases.clone();
+        for (o, row) in output.iter_mut().zip(self.weights.chunks(self.inputs)) {
+            *o += row.iter().zip(input).map(|(w, x)| w * x).sum::<f32>();
+        }
+        output
+    }
+
+    // steps against the gradient of the output and returns the gradient of
+    // the input
+    fn backward(&mut self, input: &[f32], grad: &[f32], rate: f32) -> Vec<f32> {
+        let mut input_grad = vec![0.0; self.inputs];
+        for (o, &g) in grad.iter().enumerate() {
+            if g == 0.0 {
+                continue;
+            }
+            let row = &mut self.weights[o * self.inputs..(o + 1) * self.inputs];
+            for ((w, x), ig) in row.iter_mut().zip(input).zip(input_grad.iter_mut()) {
+                *ig += *w * g;
+                *w -= rate * g * x;
+            }
+            self.biases[o] -= rate * g;
+        }
+        input_grad
+    }
+}
+
+// What one forward pass computed, kept for the backward pass
+struct Pass {
+    // input of every trunk layer followed by the output of the last one
+    activations: Vec<Vec<f32>>,
+    logits: Vec<f32>,
+    value: f32,
+}
+
+// A small multilayer perceptron with tanh hidden layers, a policy head over
+// the env actions and a value head estimating the game's outcome in [-1, 1]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mlp {
+    trunk: Vec<Layer>,
+    policy: Layer,
+    value: Layer,
+}
+
+impl Mlp {
+    // returns a randomly initialized network for observations of the input
+    // length with the input hidden layer sizes
+    pub fn new(inputs: usize, hidden: &[usize], seed: u64) -> Mlp {
+        let mut rng = GameRng::from_seed(seed);
+        let mut trunk = vec![];
+        let mut size = inputs;
+        for &h in hidden {
+            trunk.push(Layer::new(size, h, &mut rng));
+            size = h;
+        }
+        Mlp {
+            trunk,
+            policy: Layer::new(size, ACTIONS, &mut rng),
+            value: Layer::new(size, 1, &mut rng),
+        }
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.trunk.first().unwrap_or(&self.policy).inputs
+    }
+
+    // returns the number of players of the game the network observes
+    pub fn players(&self) -> Option<usize> {
+        (2..53).find(|&p| env::observation_len(p) == self.inputs())
+    }
+
+    fn forward(&self, input: &[f32]) -> Pass {
+        let mut activations = vec![input.to_vec()];
+        for layer in &self.trunk {
+            let output = layer.forward(activations.last().unwrap());
+            activations.push(output.into_iter().map(|x| x.tanh()).collect());
+        }
+        let last = activations.last().unwrap();
+        let logits = self.policy.forward(last);
+        let value = self.value.forward(last)[0].tanh();
+        Pass {
+            activations,
+            logits,
+            value,
+        }
+    }
+
+    // returns the probability of every action, zero for masked ones, and the
+    // estimated value of the observation
+    pub fn evaluate(&self, input: &[f32], mask: &[bool]) -> (Vec<f32>, f32) {
+        let pass = self.forward(input);
+        (masked_softmax(&pass.logits, mask), pass.value)
+    }
+
+    // One step of policy gradient with a value baseline: makes `action` more
+    // likely when `outcome` beats the estimated value, and moves the value
+    // towards the outcome. Returns the squared value error.
+    pub fn train(&mut self, input: &[f32], mask: &[bool], action: usize, outcome: f32, rate: f32)
+                 -> f32 {
+        let pass = self.forward(input);
+        let probs = masked_softmax(&pass.logits, mask);
+        let advantage = outcome - pass.value;
+        let policy_grad: Vec<f32> = probs.iter()
+            .enumerate()
+            .map(|(a, &p)| (p - if a == action { 1.0 } else { 0.0 }) * advantage)
+            .collect();
+        let value_grad = [(pass.value - outcome) * (1.0 - pass.value * pass.value)];
+        let last = pass.activations.last().unwrap();
+        let mut grad = self.policy.backward(last, &policy_grad, rate);
+        for (g, v) in grad.iter_mut().zip(self.value.backward(last, &value_grad, rate)) {
+            *g += v;
+        }
+        for (i, layer) in self.trunk.iter_mut().enumerate().rev() {
+            let output = &pass.activations[i + 1];
+            for (g, y) in grad.iter_mut().zip(output) {
+                *g *= 1.0 - y * y;
+            }
+            grad = layer.backward(&pass.activations[i], &grad, rate);
+        }
+        advantage * advantage
+    }
+
+    // Writes the weights as text: the header, a line of layer sizes, then a
+    // line of weights followed by a line of biases for every layer, trunk
+    // first, then the policy and value heads
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{}", WEIGHTS_HEADER)?;
+        let mut sizes = vec![self.inputs()];
+        sizes.extend(self.trunk.iter().map(|l| l.outputs));
+        let sizes: Vec<String> = sizes.iter().map(|s| s.to_string()).collect();
+        writeln!(out, "{}", sizes.join(" "))?;
+        for layer in self.trunk.iter().chain(Some(&self.policy)).chain(Some(&self.value)) {
+            let weights: Vec<String> = layer.weights.iter().map(|w| w.to_string()).collect();
+            writeln!(out, "{}", weights.join(" "))?;
+            let biases: Vec<String> = layer.biases.iter().map(|b| b.to_string()).collect();
+            writeln!(out, "{}", biases.join(" "))?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: BufRead>(input: &mut R) -> io::Result<Mlp> {
+        let mut lines = input.lines();
+        let mut next_line = || -> io::Result<String> {
+            match lines.next() {
+                Some(line) => line,
+                None => Err(invalid("weights file ended early")),
+            }
+        };
+        if next_line()?.trim() != WEIGHTS_HEADER {
+            return Err(invalid("not a bullpoker weights file"));
+        }
+        let sizes = parse_numbers::<usize>(&next_line()?)?;
+        if sizes.is_empty() {
+            return Err(invalid("missing layer sizes"));
+        }
+        let mut shapes: Vec<(usize, usize)> = sizes.windows(2).map(|w| (w[0], w[1])).collect();
+        let last = sizes[sizes.len() - 1];
+        shapes.push((last, ACTIONS));
+        shapes.push((last, 1));
+        let mut layers = vec![];
+        for (inputs, outputs) in shapes {
+            let weights = parse_numbers::<f32>(&next_line()?)?;
+            let biases = parse_numbers::<f32>(&next_line()?)?;
+            if weights.len() != inputs * outputs || biases.len() != outputs {
+                return Err(invalid("layer does not match its size"));
+            }
+            layers.push(Layer {
+                inputs,
+                outputs,
+                weights,
+                biases,
+            });
+        }
+        let value = layers.pop().unwrap();
+        let policy = layers.pop().unwrap();
+        Ok(Mlp {
+            trunk: layers,
+            policy,
+            value,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Mlp> {
+        Mlp::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+// How to train a network by self-play
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainConfig {
+    pub players: u8,
+    pub cards: u8,
+    pub rules: Rules,
+    pub hidden: Vec<usize>,
+    pub games: usize,
+    pub learning_rate: f32,
+    pub seed: u64,
+}
+
+impl Default for TrainConfig {
+    fn default() -> TrainConfig {
+        TrainConfig {
+            players: 2,
+            cards: 3,
+            rules: Rules::default(),
+            hidden: vec![64, 32],
+            games: 10_000,
+            learning_rate: 0.01,
+            seed: 0,
+        }
+    }
+}
+
+// Trains a new network by letting it play every seat of seeded games and
+// learning from each game once it ends. The same config always gives the same
+// weights. `progress` is called after every game with the game number and
+// the mean squared value error of the game.
+pub fn train(config: &TrainConfig, progress: &mut dyn FnMut(usize, f32)) -> Mlp {
+    let players = config.players as usize;
+    let mut rng = GameRng::from_seed(config.seed);
+    let mut net = Mlp::new(env::observation_len(players), &config.hidden, rng.next_u64());
+    for game in 0..config.games {
+        let mut state = match GameState::seeded(config.players, config.cards, config.rules,
+                                                rng.next_u64()) {
+            Some(state) => state,
+            None => return net,
+        };
+        // (player, observation, mask, action) of every decision
+        let mut decisions = vec![];
+        let mut places = vec![0; players];
+        let mut moves = 0;
+        while state.winner().is_none() && moves < ::arena::MAX_MOVES {
+            let view = state.view(state.current_turn);
+            let input = env::encode_view(&view, players);
+            let mask = legal_mask(&view);
+            let (probs, _) = net.evaluate(&input, &mask);
+            let action = sample(&probs, &mut rng);
+            decisions.push((view.player, input, mask, action));
+            state = state.handle_gamemove(env::action_move(action));
+            moves += 1;
+            for (name, place) in places.iter_mut().enumerate() {
+                if *place == 0 && !state.players.iter().any(|p| p.name as usize == name) {
+                    *place = state.players.len() + 1;
+                }
+            }
+        }
+        for player in &state.players {
+            places[player.name as usize] = 1;
+        }
+        let mut error = 0.0;
+        for &(player, ref input, ref mask, action) in &decisions {
+            // 1 for the winner down to -1 for the first player out
+            let place = places[player as usize];
+            let outcome = 1.0 - 2.0 * (place - 1) as f32 / (players - 1) as f32;
+            error += net.train(input, mask, action, outcome, config.learning_rate);
+        }
+        progress(game, error / decisions.len().max(1) as f32);
+    }
+    net
+}
+
+// Plays by a trained network, sampling from its policy or, when greedy,
+// always taking the most likely move
+#[derive(Clone, Debug)]
+pub struct NnBot {
+    net: Mlp,
+    players: usize,
+    greedy: bool,
+    rng: GameRng,
+}
+
+impl NnBot {
+    // returns None if the network does not fit any number of players
+    pub fn new(net: Mlp, greedy: bool, seed: u64) -> Option<NnBot> {
+        let players = net.players()?;
+        Some(NnBot {
+            net,
+            players,
+            greedy,
+            rng: GameRng::from_seed(seed),
+        })
+    }
+}
+
+impl Strategy for NnBot {
+    fn name(&self) -> String {
+        "nn".to_owned()
+    }
+
+    fn choose(&mut self, view: &PlayerView, _rules: &Rules) -> GameMove {
+        let mask = legal_mask(view);
+        // a table bigger than the network was trained for cannot be encoded
+        if view.counts.iter().any(|&(name, _)| name as usize >= self.players) {
+            return match view.min_raise() {
+                Some(hv) if view.current_bet.is_none() => GameMove::Bet(hv),
+                _ => GameMove::Call(),
+            };
+        }
+        let input = env::encode_view(view, self.players);
+        let (probs, _) = self.net.evaluate(&input, &mask);
+        let action = if self.greedy {
+            (0..ACTIONS).max_by(|&a, &b| probs[a].partial_cmp(&probs[b]).unwrap()).unwrap()
+        } else {
+            sample(&probs, &mut self.rng)
+        };
+        env::action_move(action)
+    }
+
+    fn new_game(&mut self, seed: u64) {
+        self.rng = GameRng::from_seed(seed);
+    }
+}
+
+// returns the env actions that are legal in the view
+pub fn legal_mask(view: &PlayerView) -> Vec<bool> {
+    let mut mask = vec![false; ACTIONS];
+    for gm in view.legal_moves() {
+        if let Some(action) = env::move_action(&gm) {
+            mask[action] = true;
+        }
+    }
+    mask
+}
+
+fn masked_softmax(logits: &[f32], mask: &[bool]) -> Vec<f32> {
+    let max = logits.iter()
+        .zip(mask)
+        .filter(|&(_, &legal)| legal)
+        .map(|(&l, _)| l)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let exps: Vec<f32> = logits.iter()
+        .zip(mask)
+        .map(|(&l, &legal)| if legal { (l - max).exp() } else { 0.0 })
+        .collect();
+    let total: f32 = exps.iter().sum();
+    exps.iter().map(|e| e / total).collect()
+}
+
+fn sample(probs: &[f32], rng: &mut GameRng) -> usize {
+    let mut roll = rng.next_f64() as f32;
+    let mut last = 0;
+    for (a, &p) in probs.iter().enumerate() {
+        if p > 0.0 {
+            if roll < p {
+                return a;
+            }
+            roll -= p;
+            last = a;
+        }
+    }
+    // rounding left a little probability over
+    last
+}
+
+fn parse_numbers<T: ::std::str::FromStr>(line: &str) -> io::Result<Vec<T>> {
+    line.split_whitespace()
+        .map(|s| s.parse().map_err(|_| invalid(&format!("bad number: {}", s))))
+        .collect()
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use game::{GameState, Rules};
+    use nn::{train, Mlp, NnBot, TrainConfig};
+    use strategy::Strategy;
+
+    #[test]
+    fn test_training_is_reproducible_and_saved() {
+        let config = TrainConfig {
+            cards: 1,
+            hidden: vec![8],
+            games: 30,
+            ..TrainConfig::default()
+        };
+        let mut errors = vec![];
+        let net = train(&config, &mut |_, error| errors.push(error));
+        assert_eq!(errors.len(), 30);
+        assert_eq!(train(&config, &mut |_, _| {}), net);
+        assert!(net != Mlp::new(net.inputs(), &[8], 0));
+
+        let mut bytes = vec![];
+        net.write_to(&mut bytes).unwrap();
+        let loaded = Mlp::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(loaded, net);
+        assert!(Mlp::read_from(&mut &bytes[..bytes.len() / 2]).is_err());
+
+        let mut bot = NnBot::new(loaded, true, 1).unwrap();
+        let state = GameState::seeded(2, 2, Rules::default(), 5).unwrap();
+        let view = state.view(state.current_turn);
+        assert!(view.legal_moves().contains(&bot.choose(&view, &state.rules)));
+    }
+}
@@ -1,8 +1,22 @@
-use std::cmp::Ordering;
+use std::cmp::{Ordering, max};
+use std::sync::OnceLock;
 
 use rand::{Rng, thread_rng};
 
-use card::{Card, Suit, Rank};
+use card::{Card, Suit, Rank, RenderOptions};
+use term;
+
+// number of bets before each kind of hand in HandValue::ladder()
+const TWO_PAIR: usize = 26;
+const THREE: usize = TWO_PAIR + 78;
+const STRAIGHT: usize = THREE + 13;
+const FLUSH: usize = STRAIGHT + 9;
+const FULL_HOUSE: usize = FLUSH + 36;
+const FOUR: usize = FULL_HOUSE + 156;
+const STRAIGHT_FLUSH: usize = FOUR + 13;
+
+// number of bets in HandValue::ladder()
+pub const LADDER_LEN: usize = STRAIGHT_FLUSH + 36;
 
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -52,6 +66,208 @@ impl Ord for BPStraightFlush {
     }
 }
 
+impl HandValue {
+    // returns every bet that can be made, from the lowest to the highest.
+    // Flushes and straight flushes with the same top rank are equal bets, so
+    // they appear once per suit next to each other.
+    pub fn ladder() -> Vec<HandValue> {
+        let suits = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+        let ranks: Vec<Rank> = (2..15).map(|r| Rank::from_u8(r).unwrap()).collect();
+        // straights need the four ranks below the top card
+        let tops: Vec<Rank> = (6..15).map(|r| Rank::from_u8(r).unwrap()).collect();
+        let mut output = vec![];
+        for &rank in &ranks {
+            output.push(HandValue::HighCard(rank));
+        }
+        for &rank in &ranks {
+            output.push(HandValue::OnePair(rank));
+        }
+        for &top in &ranks {
+            for &bot in ranks.iter().filter(|&&r| r < top) {
+                output.push(HandValue::TwoPair(top, bot));
+            }
+        }
+        for &rank in &ranks {
+            output.push(HandValue::ThreeOfAKind(rank));
+        }
+        for &rank in &tops {
+            output.push(HandValue::Straight(rank));
+        }
+        // lower flushes are harder to make, so they rank higher
+        for &rank in tops.iter().rev() {
+            for &suit in &suits {
+                let card = Card { suit, rank };
+                output.push(HandValue::Flush(BPFlush { card }));
+            }
+        }
+        for &three in &ranks {
+            for &two in ranks.iter().filter(|&&r| r != three) {
+                output.push(HandValue::FullHouse(three, two));
+            }
+        }
+        for &rank in &ranks {
+            output.push(HandValue::FourOfAKind(rank));
+        }
+        for &rank in tops.iter().rev() {
+            for &suit in &suits {
+                let card = Card { suit, rank };
+                output.push(HandValue::StraightFlush(BPStraightFlush { card }));
+            }
+        }
+        output
+    }
+
+    // returns HandValue::ladder(), built the first time it is needed
+    pub fn shared_ladder() -> &'static [HandValue] {
+        static LADDER: OnceLock<Vec<HandValue>> = OnceLock::new();
+        LADDER.get_or_init(HandValue::ladder)
+    }
+
+    // Returns disjoint groups of cards with how many of each group must be
+    // among the cards for the handvalue to exist, or None if it never can.
+    // This is the same test as Hand::contains_handvalue.
+    pub fn card_groups(&self) -> Option<Vec<(Vec<Card>, usize)>> {
+        let rank_cards = |rank: Rank| Card::get_all_with_rank(rank).to_vec();
+        // the same rank twice only has to meet the larger requirement
+        let two_ranks = |first: Rank, first_need: usize, second: Rank, second_need: usize| {
+            if first == second {
+                vec![(rank_cards(first), max(first_need, second_need))]
+            } else {
+                vec![(rank_cards(first), first_need), (rank_cards(second), second_need)]
+            }
+        };
+        let groups = match *self {
+            HandValue::HighCard(rank) => vec![(rank_cards(rank), 1)],
+            HandValue::OnePair(rank) => vec![(rank_cards(rank), 2)],
+            HandValue::ThreeOfAKind(rank) => vec![(rank_cards(rank), 3)],
+            HandValue::FourOfAKind(rank) => vec![(rank_cards(rank), 4)],
+            HandValue::TwoPair(top, bot) => two_ranks(top, 2, bot, 2),
+            HandValue::FullHouse(three, two) => two_ranks(three, 3, two, 2),
+            HandValue::Straight(rank) => {
+                if rank < Rank::Six {
+                    return None;
+                }
+                (rank.to_u8() - 4..rank.to_u8() + 1)
+                    .map(|r| (rank_cards(Rank::from_u8(r).unwrap()), 1))
+                    .collect()
+            }
+            HandValue::Flush(bff) => {
+                let top = bff.card;
+                let lower = (2..top.rank.to_u8())
+                    .map(|r| {
+                        Card {
+                            suit: top.suit,
+                            rank: Rank::from_u8(r).unwrap(),
+                        }
+                    })
+                    .collect();
+                vec![(vec![top], 1), (lower, 4)]
+            }
+            HandValue::StraightFlush(bfsf) => {
+                let top = bfsf.card;
+                if top.rank < Rank::Six {
+                    return None;
+                }
+                (top.rank.to_u8() - 4..top.rank.to_u8() + 1)
+                    .map(|r| {
+                        let card = Card {
+                            suit: top.suit,
+                            rank: Rank::from_u8(r).unwrap(),
+                        };
+                        (vec![card], 1)
+                    })
+                    .collect()
+            }
+        };
+        Some(groups)
+    }
+
+    // returns the position of the handvalue in HandValue::ladder(), or None
+    // if the handvalue is not a valid bet
+    pub fn ladder_index(&self) -> Option<usize> {
+        let r = |rank: Rank| (rank.to_u8() - 2) as usize;
+        // flushes count down from the ace, one bet per suit
+        let suited = |card: Card| {
+            if card.rank < Rank::Six {
+                None
+            } else {
+                Some((14 - card.rank.to_u8() as usize) * 4 + card.suit.to_index() as usize)
+            }
+        };
+        match *self {
+            HandValue::HighCard(rank) => Some(r(rank)),
+            HandValue::OnePair(rank) => Some(13 + r(rank)),
+            HandValue::TwoPair(top, bot) => {
+                if top <= bot {
+                    return None;
+                }
+                Some(TWO_PAIR + r(top) * (r(top) - 1) / 2 + r(bot))
+            }
+            HandValue::ThreeOfAKind(rank) => Some(THREE + r(rank)),
+            HandValue::Straight(rank) => {
+                if rank < Rank::Six {
+                    return None;
+                }
+                Some(STRAIGHT + r(rank) - 4)
+            }
+            HandValue::Flush(bff) => suited(bff.card).map(|i| FLUSH + i),
+            HandValue::FullHouse(three, two) => {
+                if three == two {
+                    return None;
+                }
+                let two_index = if two < three { r(two) } else { r(two) - 1 };
+                Some(FULL_HOUSE + r(three) * 12 + two_index)
+            }
+            HandValue::FourOfAKind(rank) => Some(FOUR + r(rank)),
+            HandValue::StraightFlush(bfsf) => suited(bfsf.card).map(|i| STRAIGHT_FLUSH + i),
+        }
+    }
+
+    // Returns the bet as plain text: the kind of hand followed by its ranks,
+    // or by its top card for flushes, e.g. "pair A", "twopair K 5" or
+    // "flush 10H". This is what players type and external bots send.
+    pub fn to_text(&self) -> String {
+        match *self {
+            HandValue::HighCard(rank) => format!("high {}", rank.to_string()),
+            HandValue::OnePair(rank) => format!("pair {}", rank.to_string()),
+            HandValue::TwoPair(top, bot) => {
+                format!("twopair {} {}", top.to_string(), bot.to_string())
+            }
+            HandValue::ThreeOfAKind(rank) => format!("triple {}", rank.to_string()),
+            HandValue::Straight(rank) => format!("straight {}", rank.to_string()),
+            HandValue::Flush(bff) => format!("flush {}", bff.card.to_text()),
+            HandValue::FullHouse(three, two) => {
+                format!("fullhouse {} {}", three.to_string(), two.to_string())
+            }
+            HandValue::FourOfAKind(rank) => format!("quad {}", rank.to_string()),
+            HandValue::StraightFlush(bfsf) => format!("straightflush {}", bfsf.card.to_text()),
+        }
+    }
+
+    // parses the output of to_text. Only bets on the ladder are accepted.
+    pub fn from_text(s: &str) -> Option<HandValue> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let rank = |i: usize| words.get(i).and_then(|w| Rank::from_str(w));
+        let card = |i: usize| words.get(i).and_then(|w| Card::from_text(w));
+        let (hv, len) = match *words.first()? {
+            "high" => (HandValue::HighCard(rank(1)?), 2),
+            "pair" => (HandValue::OnePair(rank(1)?), 2),
+            "twopair" => (HandValue::TwoPair(rank(1)?, rank(2)?), 3),
+            "triple" => (HandValue::ThreeOfAKind(rank(1)?), 2),
+            "straight" => (HandValue::Straight(rank(1)?), 2),
+            "flush" => (HandValue::Flush(BPFlush { card: card(1)? }), 2),
+            "fullhouse" => (HandValue::FullHouse(rank(1)?, rank(2)?), 3),
+            "quad" => (HandValue::FourOfAKind(rank(1)?), 2),
+            "straightflush" => (HandValue::StraightFlush(BPStraightFlush { card: card(1)? }), 2),
+            _ => return None,
+        };
+        if words.len() != len || hv.ladder_index().is_none() {
+            return None;
+        }
+        Some(hv)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Hand {
     pub cards: Vec<Card>,
@@ -77,6 +293,13 @@ impl Hand {
         Hand { cards: cards }
     }
 
+    // returns a standard 52 card deck shuffled with the input rng
+    pub fn get_shuffled_deck<R: Rng>(rng: &mut R) -> Hand {
+        let mut cards: Vec<Card> = (0..52).map(|i| Card::from_index(i).unwrap()).collect();
+        rng.shuffle(&mut cards);
+        Hand { cards }
+    }
+
     // returns a random hand with length of the input
     pub fn random_hand(size: usize) -> Hand {
         let mut full_deck = Hand::get_full_deck();
@@ -90,12 +313,12 @@ impl Hand {
 
     // returns a hand from input deck with length of the input
     pub fn hand_from(deck: &mut Hand, size: usize) -> Hand {
-        let mut hand = Hand { cards: vec![] };
-        for i in 0..size {
-            hand.cards.push(deck.cards[i]);
-            deck.cards.remove(i);
-        }
-        hand
+        Hand { cards: deck.cards.drain(..size).collect() }
+    }
+
+    // returns the set of cards in the hand as a bitmask of Card::to_index
+    pub fn to_mask(&self) -> u64 {
+        self.cards.iter().fold(0, |mask, card| mask | (1 << card.to_index()))
     }
 
     // adds the input cards to the deck
@@ -105,15 +328,52 @@ impl Hand {
         }
     }
 
-    // print horizontal representation of cards to stdout
+    // print horizontal representation of cards to stdout, wrapped to the
+    // width of the terminal
     pub fn to_string(&self) -> String {
+        self.render(&RenderOptions::for_terminal())
+    }
+
+    // returns the cards laid out horizontally using the input options.
+    // Boxed cards that do not fit in opts.width continue on another row.
+    pub fn render(&self, opts: &RenderOptions) -> String {
         let mut output = "".to_owned();
-        for i in 0..5 {
+        if opts.compact {
+            let mut line_len = 0;
             for card in &self.cards {
-                output.push_str(&card.to_single_string(i));
-                output.push_str(" ");
+                let card_str = card.to_compact_string(opts);
+                let card_len = term::visible_len(&card_str);
+                if line_len > 0 {
+                    match opts.width {
+                        Some(width) if line_len + 1 + card_len > width => {
+                            output.push('\n');
+                            line_len = 0;
+                        }
+                        _ => {
+                            output.push(' ');
+                            line_len += 1;
+                        }
+                    }
+                }
+                output.push_str(&card_str);
+                line_len += card_len;
+            }
+            output.push('\n');
+            return output;
+        }
+        // each boxed card is 7 columns wide plus a separating space
+        let per_row = match opts.width {
+            Some(width) => max(1, (width + 1) / 8),
+            None => max(1, self.cards.len()),
+        };
+        for row in self.cards.chunks(per_row) {
+            for i in 0..5 {
+                for card in row {
+                    output.push_str(&card.render_line(i, opts));
+                    output.push(' ');
+                }
+                output.push('\n');
             }
-            output.push_str("\n");
         }
         output
     }
@@ -295,8 +555,8 @@ impl Hand {
 
 #[cfg(test)]
 mod test {
-    use poker::{Hand, HandValue, BPFlush, BPStraightFlush};
-    use card::{Card, Suit, Rank};
+    use poker::{Hand, HandValue, BPFlush, BPStraightFlush, LADDER_LEN};
+    use card::{Card, Suit, Rank, RenderOptions};
 
     #[test]
     fn test_contains_high_card() {
@@ -491,10 +751,20 @@ mod test {
         });
         let hand = Hand { cards: cards };
         assert!(hand.contains_handvalue(
-            &HandValue::Flush(BPFlush { rank: Rank::Ace }),
+            &HandValue::Flush(BPFlush {
+                card: Card {
+                    rank: Rank::Ace,
+                    suit: Suit::Diamonds,
+                },
+            }),
         ));
         assert!(!hand.contains_handvalue(
-            &HandValue::Flush(BPFlush { rank: Rank::Jack }),
+            &HandValue::Flush(BPFlush {
+                card: Card {
+                    rank: Rank::Jack,
+                    suit: Suit::Diamonds,
+                },
+            }),
         ));
     }
     #[test]
@@ -604,10 +874,20 @@ mod test {
         });
         let hand = Hand { cards: cards };
         assert!(hand.contains_handvalue(&HandValue::StraightFlush(
-            BPStraightFlush { rank: Rank::Ace },
+            BPStraightFlush {
+                card: Card {
+                    rank: Rank::Ace,
+                    suit: Suit::Diamonds,
+                },
+            },
         )));
         assert!(!hand.contains_handvalue(&HandValue::StraightFlush(
-            BPStraightFlush { rank: Rank::King },
+            BPStraightFlush {
+                card: Card {
+                    rank: Rank::King,
+                    suit: Suit::Diamonds,
+                },
+            },
         )));
     }
     #[test]
@@ -615,4 +895,82 @@ mod test {
         let hand = Hand::get_full_deck();
         assert_eq!(hand.cards.len(), 52)
     }
+    #[test]
+    fn test_ladder_is_sorted() {
+        let ladder = HandValue::ladder();
+        assert_eq!(ladder.len(), 367);
+        for pair in ladder.windows(2) {
+            assert!(pair[0] <= pair[1]);
+        }
+    }
+    #[test]
+    fn test_text_round_trip() {
+        for hv in HandValue::ladder() {
+            assert_eq!(HandValue::from_text(&hv.to_text()), Some(hv));
+        }
+        assert_eq!(HandValue::from_text("pair 10"), Some(HandValue::OnePair(Rank::Ten)));
+        assert_eq!(HandValue::from_text("twopair 5 K"), None);
+        assert_eq!(HandValue::from_text("flush 3H"), None);
+        assert_eq!(HandValue::from_text("pair A A"), None);
+        assert_eq!(HandValue::from_text("quad"), None);
+    }
+
+    #[test]
+    fn test_ladder_index() {
+        assert_eq!(HandValue::ladder().len(), LADDER_LEN);
+        assert_eq!(HandValue::shared_ladder(), &HandValue::ladder()[..]);
+        for (i, hv) in HandValue::ladder().iter().enumerate() {
+            assert_eq!(hv.ladder_index(), Some(i));
+        }
+        assert_eq!(HandValue::TwoPair(Rank::Two, Rank::Ten).ladder_index(), None);
+        assert_eq!(HandValue::FullHouse(Rank::Ten, Rank::Ten).ladder_index(), None);
+        assert_eq!(HandValue::Straight(Rank::Five).ladder_index(), None);
+    }
+    #[test]
+    fn test_render_compact() {
+        let mut cards = vec![];
+        cards.push(Card {
+            rank: Rank::Ace,
+            suit: Suit::Spades,
+        });
+        cards.push(Card {
+            rank: Rank::King,
+            suit: Suit::Diamonds,
+        });
+        cards.push(Card {
+            rank: Rank::Ten,
+            suit: Suit::Hearts,
+        });
+        let hand = Hand { cards: cards };
+        let mut opts = RenderOptions {
+            compact: true,
+            ..RenderOptions::default()
+        };
+        assert_eq!(hand.render(&opts), "A♠ K♦ 10♥\n");
+        opts.ascii = true;
+        assert_eq!(hand.render(&opts), "AS KD 10H\n");
+        opts.width = Some(6);
+        assert_eq!(hand.render(&opts), "AS KD\n10H\n");
+        opts.color = true;
+        opts.width = None;
+        assert_eq!(
+            hand.render(&opts),
+            "\x1b[39mAS\x1b[0m \x1b[31mKD\x1b[0m \x1b[31m10H\x1b[0m\n"
+        );
+    }
+    #[test]
+    fn test_render_wraps_boxes() {
+        let hand = Hand::random_hand(7);
+        let opts = RenderOptions {
+            width: Some(30),
+            ..RenderOptions::default()
+        };
+        let output = hand.render(&opts);
+        let lines: Vec<&str> = output.lines().collect();
+        // 3 cards fit in 30 columns, so 7 cards take 3 rows of 5 lines
+        assert_eq!(lines.len(), 15);
+        for line in lines {
+            assert!(line.chars().count() <= 30);
+        }
+    }
 }
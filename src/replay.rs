@@ -0,0 +1,160 @@
+use card::RenderOptions;
+use game::{Event, GameState};
+use logic;
+use poker::Hand;
+use reveal::Reveal;
+
+// A recorded game that can be stepped through move by move. The moves are
+// the bets and calls of the history; step 0 is the first deal, before anyone
+// has moved.
+pub struct Replay {
+    // the game as it was saved, for its history and player names
+    pub game: GameState,
+    // indexes in the history of every bet and call
+    pub moves: Vec<usize>,
+}
+
+impl Replay {
+    pub fn new(game: GameState) -> Replay {
+        let moves = game.history
+            .iter()
+            .enumerate()
+            .filter(|&(_, e)| !matches!(*e, Event::Deal(_)))
+            .map(|(i, _)| i)
+            .collect();
+        Replay { game, moves }
+    }
+
+    // returns the number of moves
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    // Describes the game after the input number of moves: the hands of the
+    // round, all of them or only the viewer's, and every bet of the round
+    // with the chance that it was there when it was made. Seen by everyone,
+    // the chance is the bettor's, from their own cards; seen by a player, it
+    // is the viewer's. A call also shows what it revealed.
+    pub fn render(&self, step: usize, viewer: Option<u8>, opts: &RenderOptions) -> String {
+        let history = &self.game.history;
+        let step = step.min(self.len());
+        let end = match step {
+            0 => history.iter().position(|e| matches!(*e, Event::Deal(_))).map_or(0, |i| i + 1),
+            _ => self.moves[step - 1] + 1,
+        };
+        let deal = match history[..end].iter().rposition(|e| matches!(*e, Event::Deal(_))) {
+            Some(deal) => deal,
+            None => return "The game has no deal to show".to_owned(),
+        };
+        let hands = match history[deal] {
+            Event::Deal(ref hands) => hands,
+            _ => unreachable!(),
+        };
+        let round = history[..deal + 1].iter().filter(|e| matches!(*e, Event::Deal(_))).count();
+        let seen_by = match viewer {
+            Some(name) => format!("{}'s view", self.game.player_name(name)),
+            None => "all cards".to_owned(),
+        };
+        let mut lines = vec![format!("Move {} of {}, round {} ({})", step, self.len(), round, seen_by)];
+        let total: usize = hands.iter().map(|(_, hand)| hand.cards.len()).sum();
+        let mut pool = Hand::empty_hand();
+        for &(name, ref hand) in hands {
+            pool.add_cards(hand.clone());
+            let cards = if viewer.is_none() || viewer == Some(name) {
+                hand.render(&RenderOptions { compact: true, width: None, ..*opts }).trim_end().to_owned()
+            } else {
+                format!("{} cards", hand.cards.len())
+            };
+            lines.push(format!("{}: {}", self.game.player_name(name), cards));
+        }
+        for i in deal + 1..end {
+            match history[i] {
+                Event::Bet(player, bet) => {
+                    // the viewer's chance if they are in the round, else the bettor's
+                    let judge = viewer.filter(|v| hands.iter().any(|&(name, _)| name == *v))
+                        .unwrap_or(player);
+                    let hand = match hands.iter().find(|&&(name, _)| name == judge) {
+                        Some((_, hand)) => hand,
+                        None => {
+                            return format!("bad history: player {} bets in a round they were \
+                                            not dealt",
+                                           player)
+                        }
+                    };
+                    let chance = logic::handvalue_prob(&bet, hand, total as u8);
+                    let mut line = format!("{} bets {}: there {:.0}% of the time for {}",
+                                           self.game.player_name(player),
+                                           bet.to_text(),
+                                           100.0 * chance,
+                                           self.game.player_name(judge));
+                    if viewer.is_none() {
+                        let there = pool.contains_handvalue(&bet);
+                        line.push_str(if there { ", and it is there" } else { ", and it is not there" });
+                    }
+                    lines.push(line);
+                }
+                Event::Call { .. } => {
+                    if let Some(reveal) = Reveal::at(history, i, &self.game.rules) {
+                        lines.push(reveal.report(&self.game, opts));
+                    }
+                }
+                Event::Deal(_) => {}
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use card::RenderOptions;
+    use game::{Event, GameMove, GameState, Rules};
+    use poker::HandValue;
+    use replay::Replay;
+
+    #[test]
+    fn test_replay_steps_and_views() {
+        let mut state = GameState::seeded(2, 2, Rules::default(), 8).unwrap();
+        for gm in &[GameMove::Bet(HandValue::from_text("high 9").unwrap()),
+                    GameMove::Bet(HandValue::from_text("pair 3").unwrap()),
+                    GameMove::Call(),
+                    GameMove::Bet(HandValue::from_text("high 2").unwrap())] {
+            state = state.handle_gamemove(*gm);
+        }
+        let replay = Replay::new(state.clone());
+        assert_eq!(replay.len(), 4);
+        let opts = RenderOptions { ascii: true, ..RenderOptions::default() };
+        let start = replay.render(0, None, &opts);
+        assert!(start.starts_with("Move 0 of 4, round 1 (all cards)"), "{}", start);
+        assert!(!start.contains("bets"));
+
+        let all = replay.render(2, None, &opts);
+        assert!(all.contains("Player 0 bets high 9: there"), "{}", all);
+        assert!(all.contains("Player 1 bets pair 3: there"), "{}", all);
+        assert!(all.contains("of the time for Player 1, and it is"), "{}", all);
+        // a player sees only their own cards, and judges bets by them
+        let mine = replay.render(2, Some(0), &opts);
+        assert!(mine.contains("Player 1: 2 cards"), "{}", mine);
+        assert!(mine.contains("of the time for Player 0"));
+        assert!(!mine.contains("it is there") && !mine.contains("it is not there"));
+
+        let call = replay.render(3, Some(0), &opts);
+        assert!(call.contains("Player 0 called Player 1's pair 3."), "{}", call);
+        let last = replay.render(4, None, &opts);
+        assert!(last.contains("round 2") && last.contains("bets high 2"), "{}", last);
+        assert_eq!(replay.render(9, None, &opts), last);
+    }
+
+    #[test]
+    fn test_replay_reports_a_bad_history() {
+        let mut state = GameState::seeded(2, 2, Rules::default(), 8).unwrap();
+        state.history.push(Event::Bet(7, HandValue::from_text("high 9").unwrap()));
+        let replay = Replay::new(state);
+        let text = replay.render(1, None, &RenderOptions::default());
+        assert!(text.starts_with("bad history: player 7"), "{}", text);
+    }
+}
@@ -0,0 +1,231 @@
+use card::{Card, Rank, RenderOptions, Suit};
+use game::{Event, GameState, Penalty, Rules};
+use poker::{Hand, HandValue};
+
+// What happened to the player who lost a call
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    LosesCard,
+    GainsCard,
+    // out of the game
+    Out,
+}
+
+// Everything that was revealed by a call: the hands of the round, whether
+// the bet was there and what it cost the loser
+#[derive(Clone, Debug)]
+pub struct Reveal {
+    pub caller: u8,
+    pub bettor: u8,
+    pub bet: HandValue,
+    pub present: bool,
+    // the hands of every player in the round
+    pub hands: Vec<(u8, Hand)>,
+    pub loser: u8,
+    pub outcome: Outcome,
+    // the highest bet that was among the cards, if any
+    pub highest: Option<HandValue>,
+}
+
+impl Reveal {
+    // returns the reveal of the call at the input index of the history
+    pub fn at(history: &[Event], index: usize, rules: &Rules) -> Option<Reveal> {
+        let (caller, bettor, bet, present) = match history.get(index) {
+            Some(&Event::Call { caller, bettor, bet, present }) => (caller, bettor, bet, present),
+            _ => return None,
+        };
+        let deal = history[..index].iter().rposition(|e| matches!(*e, Event::Deal(_)))?;
+        let hands = match history[deal] {
+            Event::Deal(ref hands) => hands.clone(),
+            _ => unreachable!(),
+        };
+        let loser = if present { caller } else { bettor };
+        let size = hands.iter().find(|&&(name, _)| name == loser).map_or(0, |(_, h)| h.cards.len());
+        let outcome = match rules.penalty {
+            Penalty::LoseCard if size <= 1 => Outcome::Out,
+            Penalty::LoseCard => Outcome::LosesCard,
+            Penalty::GainCard if size >= rules.max_hand as usize => Outcome::Out,
+            Penalty::GainCard => Outcome::GainsCard,
+        };
+        let mut reveal = Reveal {
+            caller,
+            bettor,
+            bet,
+            present,
+            hands,
+            loser,
+            outcome,
+            highest: None,
+        };
+        let pool = reveal.pool();
+        reveal.highest = HandValue::ladder().into_iter().rev().find(|hv| pool.contains_handvalue(hv));
+        Some(reveal)
+    }
+
+    // returns the reveal of the last call of the game, if there has been one
+    pub fn last(state: &GameState) -> Option<Reveal> {
+        let index = state.history.iter().rposition(|e| matches!(*e, Event::Call { .. }))?;
+        Reveal::at(&state.history, index, &state.rules)
+    }
+
+    // returns every card of the round
+    pub fn pool(&self) -> Hand {
+        let mut pool = Hand::empty_hand();
+        for (_, hand) in &self.hands {
+            pool.add_cards(hand.clone());
+        }
+        pool
+    }
+
+    // Returns, for every group of cards the bet needs, how many it needs and
+    // which of them were among the cards
+    pub fn requirements(&self) -> Vec<(Vec<Card>, usize, Vec<Card>)> {
+        let pool = self.pool();
+        self.bet
+            .card_groups()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(cards, need)| {
+                let found = cards.iter().filter(|c| pool.cards.contains(c)).cloned().collect();
+                (cards, need, found)
+            })
+            .collect()
+    }
+
+    // Returns the report shown to the players: the cards grouped by rank and
+    // by suit with the cards of the bet in brackets, what the bet needed and
+    // found, who was right and what it cost the loser
+    pub fn report(&self, state: &GameState, opts: &RenderOptions) -> String {
+        let mut lines = vec![];
+        for &(name, ref hand) in &self.hands {
+            lines.push(format!("{}: {}", state.player_name(name), self.cards_text(&hand.cards, opts)));
+        }
+        let pool = self.pool();
+        let ranks: Vec<String> = (2..15)
+            .rev()
+            .filter_map(|r| {
+                let rank = Rank::from_u8(r).unwrap();
+                let cards: Vec<Card> = pool.cards.iter().filter(|c| c.rank == rank).cloned().collect();
+                if cards.is_empty() {
+                    None
+                } else {
+                    Some(self.cards_text(&cards, opts))
+                }
+            })
+            .collect();
+        lines.push(format!("By rank: {}", ranks.join(" | ")));
+        let suits: Vec<String> = (0..4)
+            .filter_map(|s| {
+                let suit = Suit::from_index(s).unwrap();
+                let mut cards: Vec<Card> = pool.cards.iter().filter(|c| c.suit == suit).cloned().collect();
+                cards.sort_by_key(|c| c.rank);
+                cards.reverse();
+                if cards.is_empty() {
+                    None
+                } else {
+                    Some(self.cards_text(&cards, opts))
+                }
+            })
+            .collect();
+        lines.push(format!("By suit: {}", suits.join(" | ")));
+
+        lines.push(format!("{} called {}'s {}. It {}:",
+                           state.player_name(self.caller),
+                           state.player_name(self.bettor),
+                           self.bet.to_text(),
+                           if self.present { "was there" } else { "was not there" }));
+        for (cards, need, found) in self.requirements() {
+            let wanted = if cards.len() == 1 {
+                cards[0].to_compact_string(opts)
+            } else if cards.iter().all(|c| c.rank == cards[0].rank) {
+                format!("{}s", cards[0].rank.to_string())
+            } else {
+                format!("lower {} cards", opts.suit_str(&cards[0].suit))
+            };
+            let shown = if found.is_empty() {
+                String::new()
+            } else {
+                format!(": {}", self.cards_text(&found, opts))
+            };
+            lines.push(format!("  {}: needed {}, found {}{}", wanted, need, found.len(), shown));
+        }
+        let (right, wrong) = if self.present {
+            (self.bettor, self.caller)
+        } else {
+            (self.caller, self.bettor)
+        };
+        lines.push(format!("{} was right; {} {}.",
+                           state.player_name(right),
+                           state.player_name(wrong),
+                           match self.outcome {
+                               Outcome::LosesCard => "loses a card",
+                               Outcome::GainsCard => "gains a card",
+                               Outcome::Out => "is out of the game",
+                           }));
+        lines.push(match self.highest {
+            Some(hv) => format!("Highest hand on the table: {}", hv.to_text()),
+            None => "No bet was on the table".to_owned(),
+        });
+        lines.join("\n")
+    }
+
+    // returns the cards on one line, the cards of the bet in brackets
+    fn cards_text(&self, cards: &[Card], opts: &RenderOptions) -> String {
+        let counted: Vec<Card> = self.requirements().into_iter().flat_map(|(_, _, found)| found).collect();
+        let texts: Vec<String> = cards.iter()
+            .map(|c| {
+                let text = c.to_compact_string(opts);
+                if counted.contains(c) {
+                    format!("[{}]", text)
+                } else {
+                    text
+                }
+            })
+            .collect();
+        texts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use card::RenderOptions;
+    use game::{GameMove, GameState, Rules};
+    use poker::HandValue;
+    use reveal::{Outcome, Reveal};
+
+    #[test]
+    fn test_reveal_report() {
+        let state = GameState::seeded(2, 3, Rules::default(), 2).unwrap();
+        assert!(Reveal::last(&state).is_none());
+        let hands: Vec<_> = state.players.iter().map(|p| p.hand.clone()).collect();
+        let pair = HandValue::from_text("pair A").unwrap();
+        let state = state.handle_gamemove(GameMove::Bet(pair));
+        let state = state.handle_gamemove(GameMove::Call());
+        let reveal = Reveal::last(&state).unwrap();
+        assert_eq!((reveal.caller, reveal.bettor), (1, 0));
+        assert_eq!(reveal.hands.len(), 2);
+        assert_eq!(reveal.hands[0].1.cards, hands[0].cards);
+        let pool = reveal.pool();
+        assert_eq!(reveal.present, pool.contains_handvalue(&pair));
+        assert_eq!(reveal.loser, if reveal.present { 1 } else { 0 });
+        assert_eq!(reveal.outcome, Outcome::LosesCard);
+        // the highest hand is there and nothing above it is
+        let highest = reveal.highest.unwrap();
+        assert!(pool.contains_handvalue(&highest));
+        let mut above = HandValue::ladder().into_iter().skip(highest.ladder_index().unwrap() + 1);
+        assert!(!above.any(|hv| pool.contains_handvalue(&hv)));
+        let (_, need, found) = reveal.requirements().remove(0);
+        assert_eq!(need, 2);
+        assert_eq!(found.len() >= 2, reveal.present);
+
+        let opts = RenderOptions { ascii: true, ..RenderOptions::default() };
+        let report = reveal.report(&state, &opts);
+        assert!(report.contains("Player 1 called Player 0's pair A."), "{}", report);
+        assert!(report.contains(&format!("Highest hand on the table: {}", highest.to_text())));
+        let wrong = if reveal.present { "Player 1" } else { "Player 0" };
+        assert!(report.contains(&format!("{} loses a card.", wrong)), "{}", report);
+        for card in &found {
+            assert!(report.contains(&format!("[{}]", card.to_text())), "{}", report);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use rand::Rng;
+
+// A small seedable random number generator (xorshift128). Unlike the
+// generators in `rand`, its state is public, so it can be copied into saved
+// games and restored exactly.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GameRng {
+    pub state: [u32; 4],
+}
+
+impl GameRng {
+    // returns a generator seeded from the input. Every seed, including 0,
+    // gives a usable state.
+    pub fn from_seed(seed: u64) -> GameRng {
+        let mut x = seed;
+        let mut state = [0u32; 4];
+        for i in 0..2 {
+            // splitmix64 spreads the seed over the whole state
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            state[2 * i] = z as u32;
+            state[2 * i + 1] = (z >> 32) as u32;
+        }
+        if state == [0; 4] {
+            state[0] = 1;
+        }
+        GameRng { state }
+    }
+
+    // returns a seed drawn from the thread rng, for games that do not ask for
+    // a particular one
+    pub fn random_seed() -> u64 {
+        ::rand::thread_rng().next_u64()
+    }
+
+    // returns a uniformly random float in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        let t = self.state[0] ^ (self.state[0] << 11);
+        self.state[0] = self.state[1];
+        self.state[1] = self.state[2];
+        self.state[2] = self.state[3];
+        let w = self.state[3];
+        self.state[3] = w ^ (w >> 19) ^ (t ^ (t >> 8));
+        self.state[3]
+    }
+}
@@ -0,0 +1,441 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use card::Card;
+use game::{Event, GameState, Penalty, Player, Rules, Seat, UndoPolicy};
+use poker::{Hand, HandValue};
+use rng::GameRng;
+use strategy::BotKind;
+
+// First line of a saved game: the format and its version. Files of another
+// version are refused rather than misread.
+pub const SAVE_FORMAT: &str = "bullpoker-game";
+pub const SAVE_VERSION: u32 = 1;
+
+// Writes the whole game as lines of text:
+//
+//   bullpoker-game 1
+//   rules <lose|gain> <max hand> <always|last|never>
+//   rng <four numbers>
+//   turn <index of the player to move>
+//   current <bet> or current none
+//   player <name> <cards> human, or player <name> <cards> bot <bot>
+//   name <shown name>
+//   deal <name>:<cards> ...
+//   bet <player> <bet>
+//   call <caller> <bettor> <bet> <present|missing>
+//   end
+//
+// with one player line per player in seat order, one name line per name and
+// the deal, bet and call lines of the history in order. Cards are written as
+// in Card::to_text and joined by commas, bets as in HandValue::to_text.
+pub fn write_game<W: Write>(state: &GameState, out: &mut W) -> io::Result<()> {
+    writeln!(out, "{} {}", SAVE_FORMAT, SAVE_VERSION)?;
+    let penalty = match state.rules.penalty {
+        Penalty::LoseCard => "lose",
+        Penalty::GainCard => "gain",
+    };
+    let undo = match state.rules.undo {
+        UndoPolicy::Always => "always",
+        UndoPolicy::LastMove => "last",
+        UndoPolicy::Never => "never",
+    };
+    writeln!(out, "rules {} {} {}", penalty, state.rules.max_hand, undo)?;
+    let rng: Vec<String> = state.rng.state.iter().map(|n| n.to_string()).collect();
+    writeln!(out, "rng {}", rng.join(" "))?;
+    writeln!(out, "turn {}", state.current_turn)?;
+    match state.current_bet {
+        Some(bet) => writeln!(out, "current {}", bet.to_text())?,
+        None => writeln!(out, "current none")?,
+    }
+    for player in &state.players {
+        let seat = match player.seat {
+            Seat::Human => "human".to_owned(),
+            Seat::Bot(ref name) => format!("bot {}", name),
+        };
+        writeln!(out, "player {} {} {}", player.name, cards_text(&player.hand), seat)?;
+    }
+    for name in &state.names {
+        writeln!(out, "name {}", name)?;
+    }
+    for event in &state.history {
+        match *event {
+            Event::Deal(ref hands) => {
+                let hands: Vec<String> = hands.iter()
+                    .map(|&(name, ref hand)| format!("{}:{}", name, cards_text(hand)))
+                    .collect();
+                writeln!(out, "deal {}", hands.join(" "))?;
+            }
+            Event::Bet(player, bet) => writeln!(out, "bet {} {}", player, bet.to_text())?,
+            Event::Call { caller, bettor, bet, present } => {
+                writeln!(out,
+                         "call {} {} {} {}",
+                         caller,
+                         bettor,
+                         bet.to_text(),
+                         if present { "present" } else { "missing" })?
+            }
+        }
+    }
+    writeln!(out, "end")
+}
+
+// Reads a game written by write_game. Explains, with the line number, why a
+// file that is not a complete saved game of this version cannot be loaded.
+pub fn read_game<R: BufRead>(input: &mut R) -> Result<GameState, String> {
+    let mut lines = vec![];
+    for line in input.lines() {
+        lines.push(line.map_err(|e| e.to_string())?);
+    }
+    let header: Vec<&str> = lines.first().map_or(vec![], |l| l.split_whitespace().collect());
+    if header.len() != 2 || header[0] != SAVE_FORMAT {
+        return Err("not a bullpoker saved game".to_owned());
+    }
+    match header[1].parse::<u32>() {
+        Ok(SAVE_VERSION) => {}
+        Ok(version) => {
+            return Err(format!("saved game format {} is not supported; this version reads \
+                                format {}",
+                               version,
+                               SAVE_VERSION))
+        }
+        Err(_) => return Err(format!("bad format version: {}", header[1])),
+    }
+    let mut rules = None;
+    let mut rng = None;
+    let mut turn = None;
+    let mut current = None;
+    let mut players = vec![];
+    let mut names = vec![];
+    let mut history = vec![];
+    let mut ended = false;
+    for (i, line) in lines.iter().enumerate().skip(1) {
+        let error = |msg: String| format!("line {}: {}", i + 1, msg);
+        let (key, rest) = match line.find(' ') {
+            Some(at) => (&line[..at], line[at + 1..].trim()),
+            None => (line.trim(), ""),
+        };
+        if ended {
+            return Err(error("text after the end of the game".to_owned()));
+        }
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        match key {
+            "rules" => rules = Some(parse_rules(&words).map_err(error)?),
+            "rng" => {
+                let numbers: Vec<u32> = words.iter().filter_map(|w| w.parse().ok()).collect();
+                if numbers.len() != 4 || words.len() != 4 || numbers == [0; 4] {
+                    return Err(error(format!("bad rng state: {}", rest)));
+                }
+                rng = Some(GameRng { state: [numbers[0], numbers[1], numbers[2], numbers[3]] });
+            }
+            "turn" => turn = Some(number(rest).map_err(error)?),
+            "current" if rest == "none" => current = Some(None),
+            "current" => current = Some(Some(bet(rest).map_err(error)?)),
+            "player" => {
+                if words.len() < 3 {
+                    return Err(error("player needs a name, cards and a seat".to_owned()));
+                }
+                let seat = match (words[2], words.len()) {
+                    ("human", 3) => Seat::Human,
+                    ("bot", len) if len > 3 => {
+                        // the bot runs to the end of the line, spaces included
+                        let kind = rest.splitn(4, ' ').nth(3).unwrap_or("").trim();
+                        match kind.parse::<BotKind>() {
+                            Ok(kind) => kind.seat(),
+                            Err(message) => return Err(error(message)),
+                        }
+                    }
+                    _ => return Err(error(format!("bad seat: {}", words[2..].join(" ")))),
+                };
+                players.push(Player {
+                    name: number(words[0]).map_err(error)?,
+                    hand: hand(words[1]).map_err(error)?,
+                    seat,
+                });
+            }
+            "name" if !rest.is_empty() => names.push(rest.to_owned()),
+            "deal" => {
+                let mut hands = vec![];
+                for word in &words {
+                    let at = word.find(':').ok_or_else(|| error(format!("bad hand: {}", word)))?;
+                    hands.push((number(&word[..at]).map_err(error)?,
+                                hand(&word[at + 1..]).map_err(error)?));
+                }
+                history.push(Event::Deal(hands));
+            }
+            "bet" if words.len() > 1 => {
+                let player = number(words[0]).map_err(error)?;
+                let text = rest.split_once(' ').map_or("", |(_, text)| text);
+                history.push(Event::Bet(player, bet(text).map_err(error)?));
+            }
+            "call" if words.len() > 3 => {
+                let present = match words[words.len() - 1] {
+                    "present" => true,
+                    "missing" => false,
+                    other => return Err(error(format!("expected present or missing, got {}", other))),
+                };
+                history.push(Event::Call {
+                    caller: number(words[0]).map_err(error)?,
+                    bettor: number(words[1]).map_err(error)?,
+                    bet: bet(&words[2..words.len() - 1].join(" ")).map_err(error)?,
+                    present,
+                });
+            }
+            "end" => ended = true,
+            "" => {}
+            _ => return Err(error(format!("unexpected line: {}", line))),
+        }
+    }
+    if !ended {
+        return Err("the saved game is incomplete".to_owned());
+    }
+    let missing = |what: &str| format!("the saved game has no {} line", what);
+    let state = GameState {
+        rules: rules.ok_or_else(|| missing("rules"))?,
+        rng: rng.ok_or_else(|| missing("rng"))?,
+        current_turn: turn.ok_or_else(|| missing("turn"))?,
+        current_bet: current.ok_or_else(|| missing("current"))?,
+        players,
+        names,
+        history,
+    };
+    check(&state)?;
+    Ok(state)
+}
+
+// returns an error if the parts of the game do not fit together
+fn check(state: &GameState) -> Result<(), String> {
+    if state.players.is_empty() {
+        return Err("the saved game has no players".to_owned());
+    }
+    if state.current_turn >= state.players.len() {
+        return Err(format!("turn {} is past the last player", state.current_turn));
+    }
+    let mut seen: Vec<u8> = vec![];
+    for player in &state.players {
+        if state.players.iter().filter(|p| p.name == player.name).count() > 1 {
+            return Err(format!("player {} is in the game twice", player.name));
+        }
+        for card in &player.hand.cards {
+            if seen.contains(&card.to_index()) {
+                return Err(format!("{} is dealt twice", card.to_text()));
+            }
+            seen.push(card.to_index());
+        }
+    }
+    check_history(state)
+}
+
+// Returns an error if the history could not have been played: every round
+// starts with a deal of players from the round before, bets are made by
+// players of the round and each beats the last, a call answers the last bet
+// and ends the round, and the last deal is the hands of the players now.
+fn check_history(state: &GameState) -> Result<(), String> {
+    let mut deal: Option<&[(u8, Hand)]> = None;
+    let mut last: Option<(u8, HandValue)> = None;
+    let mut called = false;
+    for (i, event) in state.history.iter().enumerate() {
+        let error = |msg: String| format!("history event {}: {}", i + 1, msg);
+        let in_round = |name: u8| deal.is_some_and(|hands| hands.iter().any(|&(n, _)| n == name));
+        match *event {
+            Event::Deal(ref hands) => {
+                if deal.is_some() && !called {
+                    return Err(error("a deal before the round was called".to_owned()));
+                }
+                let mut seen: Vec<u8> = vec![];
+                for (j, &(name, ref hand)) in hands.iter().enumerate() {
+                    if hands[..j].iter().any(|&(n, _)| n == name) {
+                        return Err(error(format!("player {} is dealt twice", name)));
+                    }
+                    if deal.is_some() && !in_round(name) {
+                        return Err(error(format!("player {} was not in the round before", name)));
+                    }
+                    for card in &hand.cards {
+                        if seen.contains(&card.to_index()) {
+                            return Err(error(format!("{} is dealt twice", card.to_text())));
+                        }
+                        seen.push(card.to_index());
+                    }
+                }
+                deal = Some(hands);
+                last = None;
+                called = false;
+            }
+            _ if deal.is_none() => {
+                return Err(error("the history does not start with a deal".to_owned()))
+            }
+            _ if called => return Err(error("a move after the round was called".to_owned())),
+            Event::Bet(player, bet) => {
+                if !in_round(player) {
+                    return Err(error(format!("player {} bets but is not in the round", player)));
+                }
+                if let Some((_, previous)) = last {
+                    if bet <= previous {
+                        return Err(error(format!("{} does not beat {}",
+                                                 bet.to_text(),
+                                                 previous.to_text())));
+                    }
+                }
+                last = Some((player, bet));
+            }
+            Event::Call { caller, bettor, bet, present } => {
+                if !in_round(caller) {
+                    return Err(error(format!("player {} calls but is not in the round", caller)));
+                }
+                if last != Some((bettor, bet)) {
+                    return Err(error(format!("player {} did not make the last bet {}",
+                                             bettor,
+                                             bet.to_text())));
+                }
+                let mut all_cards = Hand::empty_hand();
+                for (_, hand) in deal.unwrap_or(&[]) {
+                    all_cards.add_cards(hand.clone());
+                }
+                if all_cards.contains_handvalue(&bet) != present {
+                    return Err(error(format!("the call of {} is marked the wrong way",
+                                             bet.to_text())));
+                }
+                called = true;
+            }
+        }
+    }
+    if let Some(hands) = deal {
+        let dealt = hands.iter().map(|&(name, ref hand)| (name, &hand.cards));
+        if called || !dealt.eq(state.players.iter().map(|p| (p.name, &p.hand.cards))) {
+            return Err("the players do not hold the last hands of the history".to_owned());
+        }
+        if state.current_bet != last.map(|(_, bet)| bet) {
+            return Err("the current bet is not the last bet of the history".to_owned());
+        }
+    }
+    Ok(())
+}
+
+pub fn save_game(state: &GameState, path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_game(state, &mut out)?;
+    out.flush()
+}
+
+pub fn load_game(path: &Path) -> Result<GameState, String> {
+    let file = File::open(path).map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+    read_game(&mut BufReader::new(file)).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse_rules(words: &[&str]) -> Result<Rules, String> {
+    if words.len() != 3 {
+        return Err("rules needs a penalty, a largest hand and an undo rule".to_owned());
+    }
+    let penalty = match words[0] {
+        "lose" => Penalty::LoseCard,
+        "gain" => Penalty::GainCard,
+        other => return Err(format!("unknown penalty: {}", other)),
+    };
+    let undo = match words[2] {
+        "always" => UndoPolicy::Always,
+        "last" => UndoPolicy::LastMove,
+        "never" => UndoPolicy::Never,
+        other => return Err(format!("unknown undo rule: {}", other)),
+    };
+    Ok(Rules {
+        penalty,
+        max_hand: number(words[1])?,
+        undo,
+    })
+}
+
+fn number<T: ::std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("expected a number, got {:?}", s))
+}
+
+fn bet(s: &str) -> Result<HandValue, String> {
+    HandValue::from_text(s).ok_or_else(|| format!("not a bet: {}", s))
+}
+
+fn hand(s: &str) -> Result<Hand, String> {
+    if s == "-" {
+        return Ok(Hand::empty_hand());
+    }
+    let cards: Option<Vec<Card>> = s.split(',').map(Card::from_text).collect();
+    match cards {
+        Some(cards) => Ok(Hand { cards }),
+        None => Err(format!("bad cards: {}", s)),
+    }
+}
+
+fn cards_text(hand: &Hand) -> String {
+    if hand.cards.is_empty() {
+        return "-".to_owned();
+    }
+    let cards: Vec<String> = hand.cards.iter().map(|c| c.to_text()).collect();
+    cards.join(",")
+}
+
+#[cfg(test)]
+mod test {
+    use game::{GameMove, GameState, Penalty, Rules};
+    use poker::HandValue;
+    use save::{read_game, write_game};
+    use strategy::BotKind;
+
+    fn text(state: &GameState) -> String {
+        let mut out = vec![];
+        write_game(state, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_saved_game_resumes_exactly() {
+        let rules = Rules { penalty: Penalty::GainCard, ..Rules::default() };
+        let mut state = GameState::seeded(3, 2, rules, 21).unwrap();
+        state.names = vec!["ann".to_owned(), "bo smith".to_owned()];
+        state.set_seat(2, "ext:python3 bot.py --fast".parse::<BotKind>().unwrap().seat());
+        state = state.handle_gamemove(GameMove::Bet(HandValue::from_text("pair 4").unwrap()));
+        state = state.handle_gamemove(GameMove::Call());
+        state = state.handle_gamemove(GameMove::Bet(HandValue::from_text("flush 10H").unwrap()));
+        let saved = text(&state);
+        let loaded = read_game(&mut saved.as_bytes()).unwrap();
+        assert_eq!(text(&loaded), saved);
+        assert_eq!(loaded.rng, state.rng);
+        assert_eq!(loaded.names, state.names);
+        assert_eq!(loaded.players[2].seat, state.players[2].seat);
+        // both games go on the same way
+        let next = state.handle_gamemove(GameMove::Call());
+        let loaded_next = loaded.handle_gamemove(GameMove::Call());
+        assert_eq!(text(&loaded_next), text(&next));
+    }
+
+    #[test]
+    fn test_reject_bad_saves() {
+        let saved = text(&GameState::seeded(2, 2, Rules::default(), 3).unwrap());
+        let read = |s: &str| read_game(&mut s.as_bytes()).err().unwrap();
+        assert_eq!(read("hello"), "not a bullpoker saved game");
+        assert!(read(&saved.replace("bullpoker-game 1", "bullpoker-game 2")).contains("format 2"));
+        assert_eq!(read(&saved.replace("end\n", "")), "the saved game is incomplete");
+        assert!(read(&saved.replace("turn 0", "turn 5")).contains("turn 5"));
+        assert!(read(&saved.replace("rules lose", "rules win")).starts_with("line 2: "));
+        let first = saved.lines().find(|l| l.starts_with("player 0")).unwrap();
+        let card = first.split(' ').nth(2).unwrap().split(',').next().unwrap();
+        let second = saved.lines().find(|l| l.starts_with("player 1")).unwrap();
+        let twice = second.replacen(second.split(' ').nth(2).unwrap().split(',').next().unwrap(),
+                                    card,
+                                    1);
+        assert!(read(&saved.replace(second, &twice)).contains("dealt twice"));
+
+        // the history has to fit the players and the current bet
+        let state = GameState::seeded(2, 2, Rules::default(), 3).unwrap()
+            .handle_gamemove(GameMove::Bet(HandValue::from_text("high 9").unwrap()));
+        let saved = text(&state);
+        assert!(read(&saved.replace("bet 0 high 9", "bet 7 high 9")).contains("player 7 bets"));
+        assert!(read(&saved.replace("bet 0 high 9", "bet 0 high 9\nbet 1 high 8"))
+            .contains("high 8 does not beat high 9"));
+        assert!(read(&saved.replace("current high 9", "current none")).contains("current bet"));
+        let deal = saved.lines().find(|l| l.starts_with("deal")).unwrap();
+        assert!(read(&saved.replace(deal, "deal 0:-")).contains("last hands"));
+        let card = deal.split(':').nth(1).unwrap().split(',').next().unwrap();
+        let last = deal.rsplit(',').next().unwrap();
+        assert!(read(&saved.replace(deal, &deal.replace(last, card))).contains("dealt twice"));
+        assert!(read(&saved.replace("end", "call 1 0 high 8 present\nend")).contains("last bet"));
+    }
+}
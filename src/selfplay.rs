@@ -0,0 +1,228 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+
+use arena;
+use env;
+use game::{GameMove, PlayerView, Rules};
+use rng::GameRng;
+use strategy::{BotKind, Strategy};
+
+// What a self-play run generates. Every game seats all of the bots, rotating
+// them one seat per game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfPlayConfig {
+    pub bots: Vec<BotKind>,
+    pub cards: u8,
+    pub rules: Rules,
+    pub shards: usize,
+    pub games_per_shard: usize,
+    pub seed: u64,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> SelfPlayConfig {
+        SelfPlayConfig {
+            bots: vec!["medium".parse().unwrap(), "bluff".parse().unwrap()],
+            cards: 3,
+            rules: Rules::default(),
+            shards: 10,
+            games_per_shard: 100,
+            seed: 0,
+        }
+    }
+}
+
+// One decision of one player
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    pub game: usize,
+    pub player: u8,
+    // env::encode_view of the player's view
+    pub observation: Vec<f32>,
+    // whether calling was legal. Legal bets are always the actions from
+    // min_bet up to env::LADDER_LEN.
+    pub can_call: bool,
+    pub min_bet: usize,
+    // env action index of the move made
+    pub action: usize,
+    // the player's finishing place in the game, 1 for the winner
+    pub place: f64,
+}
+
+impl Decision {
+    // Writes the decision as one line of JSON. Observations are mostly zeros,
+    // so only the nonzero entries are written, as [index, value] pairs.
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let nonzero: Vec<String> = self.observation
+            .iter()
+            .enumerate()
+            .filter(|&(_, &v)| v != 0.0)
+            .map(|(i, v)| format!("[{},{}]", i, v))
+            .collect();
+        writeln!(out,
+                 "{{\"game\":{},\"player\":{},\"observation\":[{}],\"can_call\":{},\
+                  \"min_bet\":{},\"action\":{},\"place\":{}}}",
+                 self.game,
+                 self.player,
+                 nonzero.join(","),
+                 self.can_call,
+                 self.min_bet,
+                 self.action,
+                 self.place)
+    }
+}
+
+// Returns the seed of a shard. Shards only depend on their own seed, so any
+// shard can be regenerated on its own.
+pub fn shard_seed(seed: u64, shard: usize) -> u64 {
+    GameRng::from_seed(seed ^ (shard as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)).next_u64()
+}
+
+// returns the file of a finished shard
+pub fn shard_path(dir: &Path, shard: usize) -> PathBuf {
+    dir.join(format!("shard-{:05}.jsonl", shard))
+}
+
+// Returns every decision of the games of a shard. Fails if a bot makes a move
+// that has no env action, rather than record it as some other move. Each bot
+// is made once per shard and readied with Strategy::new_game before every
+// game.
+pub fn play_shard(config: &SelfPlayConfig, shard: usize) -> io::Result<Vec<Decision>> {
+    let mut rng = GameRng::from_seed(shard_seed(config.seed, shard));
+    let n = config.bots.len();
+    let mut pool: Vec<Option<Box<dyn Strategy>>> = (0..n).map(|_| None).collect();
+    let mut output = vec![];
+    for i in 0..config.games_per_shard {
+        let game = shard * config.games_per_shard + i;
+        let seed = rng.next_u64();
+        // the bot in each seat, drawing their seeds from the game's like
+        // arena::play_game_with
+        let mut bot_rng = GameRng::from_seed(seed);
+        let mut bots: Vec<Box<dyn Strategy>> = (0..n)
+            .map(|seat| {
+                let index = (game + seat) % n;
+                let seed = bot_rng.next_u64();
+                match pool[index].take() {
+                    Some(mut bot) => {
+                        bot.new_game(seed);
+                        bot
+                    }
+                    None => config.bots[index].build(seed),
+                }
+            })
+            .collect();
+        let start = output.len();
+        let mut unencodable = None;
+        let mut record = |view: &PlayerView, gm: &GameMove| {
+            let action = match env::move_action(gm) {
+                Some(action) => action,
+                None => {
+                    let error = format!("game {}: player {} made {:?}, which has no action",
+                                        game,
+                                        view.player,
+                                        gm);
+                    unencodable.get_or_insert(error);
+                    return;
+                }
+            };
+            let min_bet = view.min_raise()
+                .and_then(|hv| hv.ladder_index())
+                .map_or(env::ACTIONS, |i| i + 1);
+            output.push(Decision {
+                game,
+                player: view.player,
+                observation: env::encode_view(view, n),
+                can_call: view.current_bet.is_some(),
+                min_bet,
+                action,
+                place: 0.0,
+            });
+        };
+        let played = arena::play_bots(&mut bots, config.cards, config.rules, seed, &mut record);
+        for (seat, bot) in bots.into_iter().enumerate() {
+            pool[(game + seat) % n] = Some(bot);
+        }
+        let (places, _) = played.map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        if let Some(error) = unencodable {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        for decision in &mut output[start..] {
+            decision.place = places[decision.player as usize];
+        }
+    }
+    Ok(output)
+}
+
+// Generates every shard missing from the directory and returns how many were
+// written. A shard is written to a temporary file and renamed once complete,
+// so an interrupted run resumes where it stopped.
+pub fn generate(config: &SelfPlayConfig, dir: &Path) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let mut written = 0;
+    for shard in 0..config.shards {
+        let path = shard_path(dir, shard);
+        if path.exists() {
+            continue;
+        }
+        let tmp = path.with_extension("jsonl.tmp");
+        let result = File::create(&tmp).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            for decision in play_shard(config, shard)? {
+                decision.write_json(&mut out)?;
+            }
+            out.flush()
+        });
+        if let Err(error) = result {
+            // a failed shard leaves no file behind
+            let _ = fs::remove_file(&tmp);
+            return Err(error);
+        }
+        fs::rename(&tmp, &path)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+
+    use selfplay::{generate, play_shard, shard_path, SelfPlayConfig};
+
+    #[test]
+    fn test_selfplay_shards_resume() {
+        let config = SelfPlayConfig {
+            shards: 3,
+            games_per_shard: 4,
+            seed: 9,
+            ..SelfPlayConfig::default()
+        };
+        let decisions = play_shard(&config, 1).unwrap();
+        assert!(!decisions.is_empty());
+        assert!(decisions.iter().all(|d| d.game >= 4 && d.game < 8));
+        assert!(decisions.iter().all(|d| d.action >= d.min_bet || (d.action == 0 && d.can_call)));
+        assert!(decisions.iter().any(|d| d.place == 1.0));
+        assert_eq!(decisions, play_shard(&config, 1).unwrap());
+
+        let dir = env::temp_dir().join(format!("bullpoker-selfplay-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(generate(&config, &dir).unwrap(), 3);
+        let first = fs::read_to_string(shard_path(&dir, 1)).unwrap();
+        assert_eq!(first.lines().count(), decisions.len());
+        // only the missing shard is generated again, with the same contents
+        fs::remove_file(shard_path(&dir, 1)).unwrap();
+        assert_eq!(generate(&config, &dir).unwrap(), 1);
+        assert_eq!(fs::read_to_string(shard_path(&dir, 1)).unwrap(), first);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // a shard that cannot be played fails and leaves no file
+        let config = SelfPlayConfig { cards: 30, ..config };
+        assert!(generate(&config, &dir).is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
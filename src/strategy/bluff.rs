@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use game::{Event, GameMove, PlayerView, Rules};
+use inference::supporting_cards;
+use logic;
+use poker::Hand;
+use rng::GameRng;
+use strategy::Strategy;
+
+// Tunable parameters of a BluffBot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BluffConfig {
+    // 0 raises to the lowest likely bet, 1 jumps to the highest likely bet
+    pub aggression: f64,
+    // chance of claiming a hand its own cards give no evidence for, against
+    // an opponent with an average record
+    pub bluff: f64,
+    // call when the current bet looks present with less than this probability
+    pub call_below: f64,
+    // bets present with at least this probability are not bluffs
+    pub raise_above: f64,
+    // how much an opponent's record counts next to its own cards when
+    // judging the opponent's bet, once the record is long
+    pub trust: f64,
+}
+
+impl Default for BluffConfig {
+    fn default() -> BluffConfig {
+        BluffConfig {
+            aggression: 0.3,
+            bluff: 0.2,
+            call_below: 0.4,
+            raise_above: 0.5,
+            trust: 1.0,
+        }
+    }
+}
+
+// How often an opponent's bets were present when the cards were revealed
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BetRecord {
+    pub bets: u32,
+    pub present: u32,
+}
+
+impl BetRecord {
+    // estimated chance that the opponent's next bet is present, starting
+    // from one present and one missing bet
+    pub fn honesty(&self) -> f64 {
+        (self.present as f64 + 1.0) / (self.bets as f64 + 2.0)
+    }
+}
+
+// Sometimes bets on hands it has no evidence for, and trusts each opponent's
+// bets as much as their past bets turned out to be present
+#[derive(Clone, Debug)]
+pub struct BluffBot {
+    pub config: BluffConfig,
+    records: HashMap<u8, BetRecord>,
+    // events of the history already counted in the records
+    learned: usize,
+    rng: GameRng,
+}
+
+impl BluffBot {
+    pub fn new(config: BluffConfig, seed: u64) -> BluffBot {
+        BluffBot {
+            config,
+            records: HashMap::new(),
+            learned: 0,
+            rng: GameRng::from_seed(seed),
+        }
+    }
+
+    // returns the record of the player's bets seen so far
+    pub fn record(&self, player: u8) -> BetRecord {
+        self.records.get(&player).cloned().unwrap_or_default()
+    }
+
+    // Counts the bets of every round that has been revealed. A round is
+    // revealed by its call, after which the history shows every hand dealt.
+    fn learn(&mut self, history: &[Event]) {
+        let end = match history.iter().rposition(|e| matches!(*e, Event::Call { .. })) {
+            Some(i) => i + 1,
+            None => return,
+        };
+        if end <= self.learned {
+            return;
+        }
+        let mut pool = Hand::empty_hand();
+        for event in &history[self.learned..end] {
+            match *event {
+                Event::Deal(ref hands) => {
+                    pool = Hand::empty_hand();
+                    for (_, hand) in hands {
+                        pool.add_cards(hand.clone());
+                    }
+                }
+                Event::Bet(player, ref bet) => {
+                    let record = self.records.entry(player).or_default();
+                    record.bets += 1;
+                    if pool.contains_handvalue(bet) {
+                        record.present += 1;
+                    }
+                }
+                Event::Call { .. } => {}
+            }
+        }
+        self.learned = end;
+    }
+}
+
+impl Strategy for BluffBot {
+    fn name(&self) -> String {
+        format!("bluff({},{})", self.config.aggression, self.config.bluff)
+    }
+
+    fn choose(&mut self, view: &PlayerView, _rules: &Rules) -> GameMove {
+        self.learn(&view.history);
+        let total = view.total_cards();
+        let seats = view.counts.len();
+        let me = view.current_turn;
+        if let Some(bet) = view.current_bet {
+            let bettor = view.counts[(me + seats - 1) % seats].0;
+            let record = self.record(bettor);
+            let own = logic::handvalue_prob(&bet, &view.hand, total);
+            // lean towards the bettor's record as it grows
+            let weight = self.config.trust * record.bets as f64 / (record.bets as f64 + 5.0);
+            let judged = (own + weight * record.honesty()) / (1.0 + weight);
+            if judged < self.config.call_below {
+                return GameMove::Call();
+            }
+        }
+        // bets its cards make likely, and bets below the first of those that
+        // its cards give no evidence for
+        let mut likely = vec![];
+        let mut bluffs = vec![];
+        for gm in view.legal_moves() {
+            if let GameMove::Bet(hv) = gm {
+                if logic::handvalue_prob(&hv, &view.hand, total) >= self.config.raise_above {
+                    likely.push(hv);
+                } else if likely.is_empty() && supporting_cards(&view.hand.cards, &hv) == 0 {
+                    bluffs.push(hv);
+                }
+            }
+        }
+        // an opponent whose own bets are often missing expects bluffs and
+        // calls more, so bluff less against them
+        let next = view.counts[(me + 1) % seats].0;
+        let bluff = (self.config.bluff * 2.0 * self.record(next).honesty()).min(1.0);
+        if !bluffs.is_empty() && self.rng.next_f64() < bluff {
+            // bluff a few steps above the current bet, further when aggressive
+            let reach = 1 + (self.config.aggression * 4.0) as usize;
+            let end = if reach < bluffs.len() { reach } else { bluffs.len() };
+            return GameMove::Bet(bluffs[self.rng.gen_range(0, end)]);
+        }
+        if !likely.is_empty() {
+            let last = likely.len() - 1;
+            let index = (self.config.aggression * last as f64).round() as usize;
+            return GameMove::Bet(likely[index.min(last)]);
+        }
+        match view.current_bet {
+            Some(_) => GameMove::Call(),
+            None => GameMove::Bet(view.min_raise().unwrap()),
+        }
+    }
+
+    fn new_game(&mut self, seed: u64) {
+        self.rng = GameRng::from_seed(seed);
+        // the records are kept by player, and the players change
+        self.records.clear();
+        self.learned = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use card::{Card, Rank};
+    use game::{Event, GameMove, PlayerView, Rules};
+    use logic;
+    use poker::{Hand, HandValue};
+    use strategy::{BotKind, Strategy};
+    use strategy::bluff::{BluffBot, BluffConfig};
+
+    fn hand(indices: &[u8]) -> Hand {
+        Hand { cards: indices.iter().map(|&i| Card::from_index(i).unwrap()).collect() }
+    }
+
+    #[test]
+    fn test_bluff_bot_calls_known_bluffer() {
+        let mine = hand(&[0, 9, 22]);
+        let mut view = PlayerView {
+            player: 0,
+            hand: mine.clone(),
+            counts: vec![(0, 3), (1, 9)],
+            current_turn: 0,
+            current_bet: None,
+            history: vec![],
+        };
+        // a bet just likely enough not to be called on its own merits
+        let bet = HandValue::ladder()
+            .into_iter()
+            .find(|hv| {
+                let p = logic::handvalue_prob(hv, &mine, 12);
+                p > 0.41 && p < 0.5
+            })
+            .unwrap();
+        view.current_bet = Some(bet);
+        view.history.push(Event::Deal(vec![(0, mine.clone())]));
+        let rules = Rules::default();
+        let mut fresh = BluffBot::new(BluffConfig::default(), 1);
+        assert!(fresh.choose(&view, &rules) != GameMove::Call());
+
+        // five revealed rounds where player 1 claimed four aces without them
+        let mut history = vec![];
+        for _ in 0..5 {
+            history.push(Event::Deal(vec![(0, hand(&[0, 1, 2])), (1, hand(&[3, 4, 5]))]));
+            history.push(Event::Bet(1, HandValue::FourOfAKind(Rank::Ace)));
+            history.push(Event::Call {
+                caller: 0,
+                bettor: 1,
+                bet: HandValue::FourOfAKind(Rank::Ace),
+                present: false,
+            });
+        }
+        history.append(&mut view.history);
+        view.history = history;
+        let mut learned = BluffBot::new(BluffConfig::default(), 1);
+        assert_eq!(learned.choose(&view, &rules), GameMove::Call());
+        let record = learned.record(1);
+        assert_eq!((record.bets, record.present), (5, 0));
+    }
+
+    #[test]
+    fn test_bluff_rates_stay_in_range() {
+        for name in &["bluff:2:0.2",
+                      "bluff:0.3:-0.1",
+                      "bluff:0.3:0.2:1.5:0.5:1",
+                      "bluff:0.3:0.2:0.4:0.5:-1"] {
+            assert!(name.parse::<BotKind>().is_err(), "{}", name);
+        }
+        assert!("bluff:1:0:0:1:4".parse::<BotKind>().is_ok());
+        // a config made in code may still be out of range; it bets the
+        // highest likely bet instead of indexing past it
+        let config = BluffConfig { aggression: 2.0, bluff: 0.0, ..BluffConfig::default() };
+        let mine = hand(&[0, 13, 26]);
+        let view = PlayerView {
+            player: 0,
+            hand: mine.clone(),
+            counts: vec![(0, 3), (1, 3)],
+            current_turn: 0,
+            current_bet: None,
+            history: vec![Event::Deal(vec![(0, mine)])],
+        };
+        let gm = BluffBot::new(config, 3).choose(&view, &Rules::default());
+        assert!(view.legal_moves().contains(&gm));
+    }
+}
@@ -0,0 +1,551 @@
+use std::io::{self, Write};
+
+use card::{Card, Rank, Suit};
+use game::{GameMove, PlayerView, Rules};
+use logic;
+use poker::{Hand, HandValue};
+use rng::GameRng;
+use strategy::Strategy;
+
+// Size of the two player game to solve
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CfrConfig {
+    // cards held by each of the two players, 1 or 2
+    pub hand_size: usize,
+    // a raise goes up by 1 to this many steps on the ladder of possible bets
+    pub max_raise: usize,
+    // after this many bets the next player has to call
+    pub max_bets: usize,
+}
+
+impl Default for CfrConfig {
+    fn default() -> CfrConfig {
+        CfrConfig {
+            hand_size: 1,
+            max_raise: 3,
+            max_bets: 6,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Action {
+    Call,
+    // raise to the bet at this position of CfrSolver::bets
+    Raise(usize),
+}
+
+// A point in the betting, reached by a sequence of raises
+#[derive(Clone, Debug)]
+struct Node {
+    bet: Option<usize>,
+    depth: usize,
+    actions: Vec<Action>,
+    // node reached by each action, None for a call
+    children: Vec<Option<usize>>,
+    // position of the node's first action among the actions of all nodes
+    offset: usize,
+}
+
+// CFR+ trainer for two player games with small hands. With at most 4 cards in
+// play no bet needs a suit, so hands are abstracted to their ranks and the
+// information set of a player is their ranks plus the bets made so far.
+#[derive(Clone, Debug)]
+pub struct CfrSolver {
+    pub config: CfrConfig,
+    // the bets that can be present with the cards in play, lowest first
+    bets: Vec<HandValue>,
+    nodes: Vec<Node>,
+    // every hand as sorted ranks
+    hands: Vec<Vec<Rank>>,
+    // (hand of the first player, hand of the second player, probability)
+    deals: Vec<(usize, usize, f64)>,
+    // whether each bet is among the cards of each pair of hands
+    present: Vec<bool>,
+    regrets: Vec<f64>,
+    strategy_sum: Vec<f64>,
+    pub iterations: usize,
+}
+
+impl CfrSolver {
+    pub fn new(config: CfrConfig) -> CfrSolver {
+        let total = 2 * config.hand_size;
+        let bets: Vec<HandValue> = HandValue::ladder()
+            .into_iter()
+            .filter(|hv| match hv.card_groups() {
+                Some(groups) => groups.iter().map(|g| g.1).sum::<usize>() <= total,
+                None => false,
+            })
+            .collect();
+        let mut solver = CfrSolver {
+            config,
+            bets,
+            nodes: vec![],
+            hands: rank_hands(config.hand_size),
+            deals: vec![],
+            present: vec![],
+            regrets: vec![],
+            strategy_sum: vec![],
+            iterations: 0,
+        };
+        solver.build_node(None, 0);
+        solver.build_deals();
+        let size = solver.nodes.iter().map(|n| n.actions.len()).sum::<usize>() *
+                   solver.hands.len();
+        solver.regrets = vec![0.0; size];
+        solver.strategy_sum = vec![0.0; size];
+        solver
+    }
+
+    fn build_node(&mut self, bet: Option<usize>, depth: usize) -> usize {
+        let id = self.nodes.len();
+        let mut actions = vec![];
+        if bet.is_some() {
+            actions.push(Action::Call);
+        }
+        if depth < self.config.max_bets {
+            let first = bet.map_or(0, |b| b + 1);
+            for next in first..self.bets.len().min(first + self.config.max_raise) {
+                actions.push(Action::Raise(next));
+            }
+        }
+        let offset = self.nodes.last().map_or(0, |n| n.offset + n.actions.len());
+        self.nodes.push(Node {
+            bet,
+            depth,
+            actions: actions.clone(),
+            children: vec![],
+            offset,
+        });
+        let mut children = vec![];
+        for action in actions {
+            children.push(match action {
+                Action::Call => None,
+                Action::Raise(next) => Some(self.build_node(Some(next), depth + 1)),
+            });
+        }
+        self.nodes[id].children = children;
+        id
+    }
+
+    // weighs every pair of rank hands by the number of ways to deal them
+    fn build_deals(&mut self) {
+        let mut total = 0.0;
+        for (i, first) in self.hands.iter().enumerate() {
+            for (j, second) in self.hands.iter().enumerate() {
+                let mut ways = 1.0;
+                for rank in 2..15 {
+                    let rank = Rank::from_u8(rank).unwrap();
+                    let a = first.iter().filter(|&&r| r == rank).count();
+                    let b = second.iter().filter(|&&r| r == rank).count();
+                    ways *= logic::choose(4, a) * logic::choose(4 - a, b);
+                }
+                if ways > 0.0 {
+                    self.deals.push((i, j, ways));
+                    total += ways;
+                }
+                // the payoff of every call only depends on which bets are there
+                let mut ranks = first.clone();
+                ranks.extend_from_slice(second);
+                let pool = if ways > 0.0 { Some(rank_hand(&ranks)) } else { None };
+                for bet in &self.bets {
+                    self.present.push(pool.as_ref().is_some_and(|p| p.contains_handvalue(bet)));
+                }
+            }
+        }
+        for deal in &mut self.deals {
+            deal.2 /= total;
+        }
+    }
+
+    fn index(&self, node: usize, hand: usize) -> usize {
+        let n = &self.nodes[node];
+        n.offset * self.hands.len() + hand * n.actions.len()
+    }
+
+    // regret matching+: play actions in proportion to their positive regret
+    fn current_strategy(&self, node: usize, hand: usize) -> Vec<f64> {
+        let start = self.index(node, hand);
+        let len = self.nodes[node].actions.len();
+        normalized(&self.regrets[start..start + len])
+    }
+
+    // returns the average strategy, which is what converges to equilibrium
+    fn average_strategy(&self, node: usize, hand: usize) -> Vec<f64> {
+        let start = self.index(node, hand);
+        let len = self.nodes[node].actions.len();
+        normalized(&self.strategy_sum[start..start + len])
+    }
+
+    // utility of a call for the first player
+    fn call_payoff(&self, node: usize, hands: (usize, usize)) -> f64 {
+        let n = &self.nodes[node];
+        let pair = hands.0 * self.hands.len() + hands.1;
+        let present = self.present[pair * self.bets.len() + n.bet.unwrap()];
+        // the caller is the player to act; the caller loses if it is present
+        let caller = n.depth % 2;
+        let caller_value = if present { -1.0 } else { 1.0 };
+        if caller == 0 { caller_value } else { -caller_value }
+    }
+
+    // runs one iteration of CFR+ over every deal
+    pub fn iterate(&mut self) {
+        self.iterations += 1;
+        let weight = self.iterations as f64;
+        for d in 0..self.deals.len() {
+            let (first, second, prob) = self.deals[d];
+            self.traverse(0, (first, second), 1.0, 1.0, prob, weight);
+        }
+        for regret in &mut self.regrets {
+            if *regret < 0.0 {
+                *regret = 0.0;
+            }
+        }
+    }
+
+    // returns the utility of the node for the first player and updates the
+    // regrets and average strategy of the player to act
+    fn traverse(&mut self, node: usize, hands: (usize, usize), reach0: f64, reach1: f64,
+                chance: f64, weight: f64)
+                -> f64 {
+        let player = self.nodes[node].depth % 2;
+        let hand = if player == 0 { hands.0 } else { hands.1 };
+        let sigma = self.current_strategy(node, hand);
+        let len = sigma.len();
+        let mut values = vec![0.0; len];
+        let mut value = 0.0;
+        for a in 0..len {
+            values[a] = match self.nodes[node].children[a] {
+                None => self.call_payoff(node, hands),
+                Some(child) => {
+                    if player == 0 {
+                        self.traverse(child, hands, reach0 * sigma[a], reach1, chance, weight)
+                    } else {
+                        self.traverse(child, hands, reach0, reach1 * sigma[a], chance, weight)
+                    }
+                }
+            };
+            value += sigma[a] * values[a];
+        }
+        let (own, other, sign) = if player == 0 {
+            (reach0, reach1, 1.0)
+        } else {
+            (reach1, reach0, -1.0)
+        };
+        let start = self.index(node, hand);
+        for a in 0..len {
+            self.regrets[start + a] += sign * other * chance * (values[a] - value);
+            self.strategy_sum[start + a] += weight * own * chance * sigma[a];
+        }
+        value
+    }
+
+    // Returns how much a best response gains against the average strategy,
+    // averaged over both seats. It is 0 at an equilibrium.
+    pub fn exploitability(&self) -> f64 {
+        let mut total = 0.0;
+        for player in 0..2 {
+            for hand in 0..self.hands.len() {
+                let opponents: Vec<(usize, f64)> = self.deals
+                    .iter()
+                    .filter(|d| if player == 0 { d.0 == hand } else { d.1 == hand })
+                    .map(|d| (if player == 0 { d.1 } else { d.0 }, d.2))
+                    .collect();
+                total += self.best_response(player, 0, hand, &opponents);
+            }
+        }
+        total / 2.0
+    }
+
+    // value for `player` of best responding from the node with the input hand,
+    // where opponents holds each opponent hand with its reach probability
+    fn best_response(&self, player: usize, node: usize, hand: usize,
+                     opponents: &[(usize, f64)])
+                     -> f64 {
+        let n = &self.nodes[node];
+        let to_act = n.depth % 2;
+        let mut action_values = vec![];
+        for a in 0..n.actions.len() {
+            let value = match n.children[a] {
+                None => {
+                    opponents.iter()
+                        .map(|&(opp, w)| {
+                            let hands = if player == 0 { (hand, opp) } else { (opp, hand) };
+                            let payoff = self.call_payoff(node, hands);
+                            w * if player == 0 { payoff } else { -payoff }
+                        })
+                        .sum()
+                }
+                Some(child) => {
+                    if to_act == player {
+                        self.best_response(player, child, hand, opponents)
+                    } else {
+                        let reached: Vec<(usize, f64)> = opponents.iter()
+                            .map(|&(opp, w)| (opp, w * self.average_strategy(node, opp)[a]))
+                            .collect();
+                        self.best_response(player, child, hand, &reached)
+                    }
+                }
+            };
+            action_values.push(value);
+        }
+        if to_act == player {
+            action_values.iter().cloned().fold(f64::NEG_INFINITY, f64::max)
+        } else {
+            // opponent reach is already in the weights of each call
+            let mut value = 0.0;
+            for (a, v) in action_values.iter().enumerate() {
+                match n.children[a] {
+                    Some(_) => value += v,
+                    None => {
+                        let called: f64 = opponents.iter()
+                            .map(|&(opp, w)| {
+                                let hands = if player == 0 { (hand, opp) } else { (opp, hand) };
+                                let payoff = self.call_payoff(node, hands);
+                                w * self.average_strategy(node, opp)[a] *
+                                if player == 0 { payoff } else { -payoff }
+                            })
+                            .sum();
+                        value += called;
+                    }
+                }
+            }
+            value
+        }
+    }
+
+    // writes the average strategy, one information set per line
+    pub fn write_strategy<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for node in 0..self.nodes.len() {
+            let bets: Vec<String> = self.path(node)
+                .iter()
+                .map(|&b| format!("{:?}", self.bets[b]))
+                .collect();
+            for (h, hand) in self.hands.iter().enumerate() {
+                let ranks: Vec<&str> = hand.iter().map(|r| r.to_string()).collect();
+                write!(out, "[{}] {}:", ranks.join(" "), bets.join(" "))?;
+                let sigma = self.average_strategy(node, h);
+                for (action, p) in self.nodes[node].actions.iter().zip(sigma) {
+                    match *action {
+                        Action::Call => write!(out, " call={:.3}", p)?,
+                        Action::Raise(b) => write!(out, " {:?}={:.3}", self.bets[b], p)?,
+                    }
+                }
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+
+    // returns the bets that lead to the node
+    fn path(&self, node: usize) -> Vec<usize> {
+        let mut output = vec![];
+        let mut current = 0;
+        while current != node {
+            let n = &self.nodes[current];
+            // children are built depth first, so the last child that starts at
+            // or before the node is its ancestor
+            let mut next = None;
+            for child in n.children.iter().flat_map(|c| c.iter()) {
+                if *child <= node {
+                    next = Some(*child);
+                }
+            }
+            current = next.unwrap();
+            output.push(self.nodes[current].bet.unwrap());
+        }
+        output
+    }
+
+    // returns the node reached by the bets, if they are all in the tree
+    fn find_node(&self, bets: &[HandValue]) -> Option<usize> {
+        let mut node = 0;
+        for bet in bets {
+            let index = self.bets.iter().position(|b| b == bet)?;
+            let n = &self.nodes[node];
+            let a = n.actions.iter().position(|&a| a == Action::Raise(index))?;
+            node = n.children[a]?;
+        }
+        Some(node)
+    }
+
+    fn hand_index(&self, hand: &Hand) -> Option<usize> {
+        let mut ranks: Vec<Rank> = hand.cards.iter().map(|c| c.rank).collect();
+        ranks.sort();
+        self.hands.iter().position(|h| *h == ranks)
+    }
+}
+
+// Plays the average strategy of a trained CfrSolver. Positions the solver
+// never reached are played by calling unlikely bets and raising the lowest
+// bet otherwise.
+#[derive(Clone, Debug)]
+pub struct CfrBot {
+    solver: CfrSolver,
+    rng: GameRng,
+}
+
+impl CfrBot {
+    pub fn new(solver: CfrSolver, seed: u64) -> CfrBot {
+        CfrBot {
+            solver,
+            rng: GameRng::from_seed(seed),
+        }
+    }
+
+    // trains a solver for the configuration and plays its strategy
+    pub fn train(config: CfrConfig, iterations: usize, seed: u64) -> CfrBot {
+        let mut solver = CfrSolver::new(config);
+        for _ in 0..iterations {
+            solver.iterate();
+        }
+        CfrBot::new(solver, seed)
+    }
+
+    // returns whether the strategy covers the view: two players, both with
+    // the number of cards it was solved for
+    fn solved(&self, view: &PlayerView) -> bool {
+        view.counts.len() == 2 &&
+        view.counts.iter().all(|&(_, count)| count == self.solver.config.hand_size)
+    }
+
+    fn fallback(&self, view: &PlayerView) -> GameMove {
+        if let Some(bet) = view.current_bet {
+            if logic::handvalue_prob(&bet, &view.hand, view.total_cards()) < 0.5 {
+                return GameMove::Call();
+            }
+        }
+        match view.min_raise() {
+            Some(hv) => GameMove::Bet(hv),
+            None => GameMove::Call(),
+        }
+    }
+}
+
+impl Strategy for CfrBot {
+    fn name(&self) -> String {
+        format!("cfr({})", self.solver.config.hand_size)
+    }
+
+    fn choose(&mut self, view: &PlayerView, _rules: &Rules) -> GameMove {
+        let bets: Vec<HandValue> = view.round_bets().iter().map(|b| b.1).collect();
+        let node = self.solver.find_node(&bets);
+        let hand = self.solver.hand_index(&view.hand);
+        let (node, hand) = match (node, hand) {
+            (Some(node), Some(hand)) if self.solved(view) => (node, hand),
+            _ => return self.fallback(view),
+        };
+        let sigma = self.solver.average_strategy(node, hand);
+        let mut roll = self.rng.next_f64();
+        let actions = &self.solver.nodes[node].actions;
+        let mut chosen = actions[actions.len() - 1];
+        for (&action, p) in actions.iter().zip(sigma) {
+            if roll < p {
+                chosen = action;
+                break;
+            }
+            roll -= p;
+        }
+        match chosen {
+            Action::Call => GameMove::Call(),
+            Action::Raise(b) => GameMove::Bet(self.solver.bets[b]),
+        }
+    }
+
+    fn new_game(&mut self, seed: u64) {
+        self.rng = GameRng::from_seed(seed);
+    }
+}
+
+// returns every multiset of ranks of the input size, as sorted ranks
+fn rank_hands(size: usize) -> Vec<Vec<Rank>> {
+    let mut output = vec![vec![]];
+    for _ in 0..size {
+        let mut next = vec![];
+        for hand in &output {
+            let lowest = hand.last().map_or(2, |r: &Rank| r.to_u8());
+            for rank in lowest..15 {
+                let mut bigger = hand.clone();
+                bigger.push(Rank::from_u8(rank).unwrap());
+                next.push(bigger);
+            }
+        }
+        output = next;
+    }
+    output
+}
+
+// a hand with the input ranks, using the suits in order for repeated ranks
+fn rank_hand(ranks: &[Rank]) -> Hand {
+    let mut cards: Vec<Card> = vec![];
+    for &rank in ranks {
+        let taken = cards.iter().filter(|c| c.rank == rank).count();
+        cards.push(Card {
+            rank,
+            suit: Suit::from_index(taken as u8).unwrap(),
+        });
+    }
+    Hand { cards }
+}
+
+fn normalized(values: &[f64]) -> Vec<f64> {
+    let positive: f64 = values.iter().filter(|&&v| v > 0.0).sum();
+    if positive > 0.0 {
+        values.iter().map(|&v| if v > 0.0 { v / positive } else { 0.0 }).collect()
+    } else {
+        vec![1.0 / values.len() as f64; values.len()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use card::Card;
+    use game::{Event, GameMove, PlayerView, Rules};
+    use poker::Hand;
+    use strategy::Strategy;
+    use strategy::cfr::{CfrBot, CfrConfig, CfrSolver};
+
+    #[test]
+    fn test_cfr_reduces_exploitability() {
+        let config = CfrConfig {
+            hand_size: 1,
+            max_raise: 2,
+            max_bets: 4,
+        };
+        let mut solver = CfrSolver::new(config);
+        solver.iterate();
+        let early = solver.exploitability();
+        for _ in 0..200 {
+            solver.iterate();
+        }
+        let late = solver.exploitability();
+        assert!(late >= -1e-9);
+        assert!(late < early / 4.0, "{} -> {}", early, late);
+        let mut out = vec![];
+        solver.write_strategy(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("call="));
+    }
+
+    #[test]
+    fn test_cfr_bot_falls_back_once_a_card_is_lost() {
+        let config = CfrConfig { hand_size: 2, ..CfrConfig::default() };
+        let hand = Hand { cards: vec![Card::from_index(0).unwrap(), Card::from_index(14).unwrap()] };
+        let mut view = PlayerView {
+            player: 0,
+            hand: hand.clone(),
+            counts: vec![(0, 2), (1, 2)],
+            current_turn: 0,
+            current_bet: None,
+            history: vec![Event::Deal(vec![(0, hand)])],
+        };
+        let opening = GameMove::Bet(view.min_raise().unwrap());
+        let mut bot = CfrBot::new(CfrSolver::new(config), 1);
+        let rules = Rules::default();
+        // the untrained strategy opens with many bets
+        assert!((0..20).any(|_| bot.choose(&view, &rules) != opening));
+        view.counts[1].1 = 1;
+        for _ in 0..20 {
+            assert_eq!(bot.choose(&view, &rules), opening);
+        }
+    }
+}
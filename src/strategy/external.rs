@@ -0,0 +1,319 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use game::{Event, GameMove, PlayerView, Rules};
+use poker::{Hand, HandValue};
+use strategy::Strategy;
+
+// Version of the protocol announced in the handshake
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// An external bot program and how long it may think
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalConfig {
+    // the program followed by its arguments
+    pub command: Vec<String>,
+    // limit for the handshake and for every move
+    pub timeout: Duration,
+}
+
+impl ExternalConfig {
+    pub fn new(command: &str) -> ExternalConfig {
+        ExternalConfig {
+            command: command.split_whitespace().map(|s| s.to_owned()).collect(),
+            timeout: Duration::from_millis(5000),
+        }
+    }
+}
+
+// A bot running as a separate process, spoken to over its stdin and stdout
+// with the line protocol described in the README. When the process fails to
+// start, crashes, times out or sends a reply that is not a legal move, the
+// engine makes a safe move for it instead: it calls if there is a bet and
+// otherwise opens with the lowest bet. A bot that crashed or timed out is
+// not asked again.
+pub struct ExternalBot {
+    pub config: ExternalConfig,
+    process: Option<Process>,
+    // history events already sent, always whole rounds
+    sent: usize,
+    // another game has begun since the last turn, to be announced
+    starting: bool,
+    // messages about moves the engine had to make for the bot
+    pub errors: Vec<String>,
+}
+
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl ExternalBot {
+    // starts the program and waits for it to answer the handshake
+    pub fn new(config: ExternalConfig) -> ExternalBot {
+        let mut bot = ExternalBot {
+            config,
+            process: None,
+            sent: 0,
+            starting: false,
+            errors: vec![],
+        };
+        match bot.spawn() {
+            Ok(process) => {
+                bot.process = Some(process);
+                match bot.exchange(&format!("bullpoker {}\n", PROTOCOL_VERSION)) {
+                    Some(ref reply) if reply == "ready" => {}
+                    Some(reply) => bot.fail(&format!("expected ready, got {:?}", reply)),
+                    None => {}
+                }
+            }
+            Err(error) => bot.fail(&error),
+        }
+        bot
+    }
+
+    fn spawn(&self) -> Result<Process, String> {
+        let (program, args) = match self.config.command.split_first() {
+            Some(split) => split,
+            None => return Err("no command given".to_owned()),
+        };
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("could not start {}: {}", program, e))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        // a thread reads the replies so waiting for one can time out
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line.trim().to_owned()).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+        Ok(Process {
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    // stops asking the process and records why
+    fn fail(&mut self, error: &str) {
+        self.errors.push(error.to_owned());
+        if let Some(mut process) = self.process.take() {
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+
+    // sends the message and returns the reply, failing the bot if there is
+    // none in time
+    fn exchange(&mut self, message: &str) -> Option<String> {
+        let timeout = self.config.timeout;
+        let result = {
+            let process = self.process.as_mut()?;
+            let sent = process.stdin.write_all(message.as_bytes());
+            match sent.and_then(|_| process.stdin.flush()) {
+                Ok(()) => process.lines.recv_timeout(timeout).map_err(|e| e.to_string()),
+                Err(error) => Err(error.to_string()),
+            }
+        };
+        match result {
+            Ok(line) => Some(line),
+            Err(error) => {
+                self.fail(&format!("no reply: {}", error));
+                None
+            }
+        }
+    }
+
+    // returns the messages describing the view, and marks the finished
+    // rounds as sent
+    fn describe(&mut self, view: &PlayerView) -> String {
+        let mut output = String::new();
+        if self.starting {
+            output.push_str("newgame\n");
+            self.starting = false;
+        }
+        let current = view.history
+            .iter()
+            .rposition(|e| matches!(*e, Event::Deal(_)))
+            .unwrap_or(0);
+        // a new game starts the history over
+        if self.sent > current {
+            self.sent = 0;
+        }
+        for event in &view.history[self.sent..current] {
+            output.push_str(&event_line(event));
+        }
+        self.sent = current;
+        output.push_str("turn\n");
+        output.push_str(&format!("player {}\n", view.player));
+        output.push_str(&format!("hand {}\n", cards_text(&view.hand)));
+        for &(name, count) in &view.counts {
+            output.push_str(&format!("count {} {}\n", name, count));
+        }
+        for &(player, bet) in &view.round_bets() {
+            output.push_str(&format!("bet {} {}\n", player, bet.to_text()));
+        }
+        output.push_str("go\n");
+        output
+    }
+}
+
+impl Strategy for ExternalBot {
+    fn name(&self) -> String {
+        self.config.command.first().cloned().unwrap_or_default()
+    }
+
+    fn choose(&mut self, view: &PlayerView, _rules: &Rules) -> GameMove {
+        let fallback = match view.current_bet {
+            Some(_) => GameMove::Call(),
+            None => GameMove::Bet(view.min_raise().unwrap()),
+        };
+        let message = self.describe(view);
+        let reply = match self.exchange(&message) {
+            Some(reply) => reply,
+            None => return fallback,
+        };
+        match parse_move(&reply) {
+            Some(gm) if view.legal_moves().contains(&gm) => gm,
+            _ => {
+                self.errors.push(format!("illegal reply {:?}", reply));
+                fallback
+            }
+        }
+    }
+
+    fn new_game(&mut self, _seed: u64) {
+        self.sent = 0;
+        self.starting = true;
+    }
+}
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            let _ = process.stdin.write_all(b"quit\n");
+            let _ = process.stdin.flush();
+            // give the bot a moment to exit on its own
+            thread::sleep(Duration::from_millis(10));
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+}
+
+// parses "call" or "bet <handvalue>"
+pub fn parse_move(line: &str) -> Option<GameMove> {
+    let line = line.trim();
+    if line == "call" {
+        return Some(GameMove::Call());
+    }
+    line.strip_prefix("bet ").and_then(HandValue::from_text).map(GameMove::Bet)
+}
+
+// returns the protocol line of an event of a finished round
+fn event_line(event: &Event) -> String {
+    match *event {
+        Event::Deal(ref hands) => {
+            let hands: Vec<String> = hands.iter()
+                .map(|&(name, ref hand)| format!("{}:{}", name, cards_text(hand).replace(' ', ",")))
+                .collect();
+            format!("reveal {}\n", hands.join(" "))
+        }
+        Event::Bet(player, bet) => format!("bet {} {}\n", player, bet.to_text()),
+        Event::Call { caller, bettor, present, .. } => {
+            format!("call {} {} {}\n", caller, bettor, if present { "present" } else { "missing" })
+        }
+    }
+}
+
+fn cards_text(hand: &Hand) -> String {
+    let cards: Vec<String> = hand.cards.iter().map(|c| c.to_text()).collect();
+    cards.join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use game::{GameState, Rules};
+    use strategy::{CallBot, Strategy};
+    use strategy::external::{ExternalBot, ExternalConfig};
+
+    // calls any bet and otherwise opens with the lowest high card
+    const SCRIPT: &str = "while read -r line; do
+        case \"$line\" in
+            'bullpoker 1') echo ready ;;
+            turn) bet=0 ;;
+            'bet '*) bet=1 ;;
+            go) if [ \"$bet\" = 1 ]; then echo call; else echo 'bet high 2'; fi ;;
+            quit) exit 0 ;;
+        esac
+    done";
+
+    fn config(script: &str) -> ExternalConfig {
+        ExternalConfig {
+            command: vec!["sh".to_owned(), "-c".to_owned(), script.to_owned()],
+            timeout: Duration::from_millis(2000),
+        }
+    }
+
+    fn play(bot: &mut ExternalBot) {
+        let mut other = CallBot;
+        let mut state = GameState::seeded(2, 2, Rules::default(), 3).unwrap();
+        while state.winner().is_none() {
+            let view = state.view(state.current_turn);
+            let gm = if view.player == 0 {
+                bot.choose(&view, &state.rules)
+            } else {
+                other.choose(&view, &state.rules)
+            };
+            assert!(view.legal_moves().contains(&gm));
+            state = state.handle_gamemove(gm);
+        }
+    }
+
+    #[test]
+    fn test_external_bot_plays_a_game() {
+        let mut bot = ExternalBot::new(config(SCRIPT));
+        play(&mut bot);
+        assert!(bot.errors.is_empty(), "{:?}", bot.errors);
+    }
+
+    #[test]
+    fn test_external_bot_failures_fall_back() {
+        // exits straight away
+        let mut bot = ExternalBot::new(config("exit 0"));
+        play(&mut bot);
+        assert!(!bot.errors.is_empty());
+        // answers the handshake, then never replies
+        let mut slow = config("read -r line; echo ready; exec sleep 5");
+        slow.timeout = Duration::from_millis(100);
+        let mut bot = ExternalBot::new(slow);
+        play(&mut bot);
+        assert_eq!(bot.errors.len(), 1);
+        // replies with nonsense
+        let mut bot = ExternalBot::new(config("read -r line; echo ready; while read -r l; do \
+                                               [ \"$l\" = go ] && echo 'bet pair 1'; done"));
+        play(&mut bot);
+        assert!(bot.errors.iter().all(|e| e.starts_with("illegal reply")));
+        // a program that does not exist
+        let bot = ExternalBot::new(ExternalConfig::new("/nonexistent/bot"));
+        assert_eq!(bot.errors.len(), 1);
+    }
+}
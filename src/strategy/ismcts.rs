@@ -0,0 +1,303 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use card::Card;
+use game::{Event, GameMove, GameState, Player, PlayerView, Rules, Seat};
+use inference::supporting_cards;
+use poker::{Hand, HandValue};
+use rng::GameRng;
+use strategy::Strategy;
+
+// How long an IsmctsBot searches before moving
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IsmctsConfig {
+    // number of sampled deals to search
+    pub iterations: usize,
+    // stop early once this much time has passed
+    pub time_limit: Option<Duration>,
+    // weight of the exploration term of UCB1
+    pub exploration: f64,
+    // bets considered at each turn besides the lowest raise
+    pub width: usize,
+    // bets a playout makes before the next player has to call
+    pub max_playout: usize,
+}
+
+impl Default for IsmctsConfig {
+    fn default() -> IsmctsConfig {
+        IsmctsConfig {
+            iterations: 2000,
+            time_limit: None,
+            exploration: 0.7,
+            width: 4,
+            max_playout: 8,
+        }
+    }
+}
+
+// A move in the search tree. The hidden cards differ between iterations, so
+// a node is only one of the choices of its parent when its move is legal.
+#[derive(Clone, Debug)]
+struct Node {
+    gm: Option<GameMove>,
+    // the player who made the move leading here
+    mover: u8,
+    children: Vec<usize>,
+    visits: f64,
+    // number of iterations in which the node could have been chosen
+    available: f64,
+    // summed rewards of the mover
+    reward: f64,
+}
+
+// Information set Monte Carlo tree search. Every iteration deals the cards it
+// cannot see at random, keeping the hand sizes, and searches the round with
+// the engine's own moves. A round scores 1 for every player except the one
+// who loses the call.
+#[derive(Clone, Debug)]
+pub struct IsmctsBot {
+    pub config: IsmctsConfig,
+    rng: GameRng,
+}
+
+impl IsmctsBot {
+    pub fn new(config: IsmctsConfig, seed: u64) -> IsmctsBot {
+        IsmctsBot {
+            config,
+            rng: GameRng::from_seed(seed),
+        }
+    }
+
+    // returns a game with the view's hands filled in by dealing the unseen
+    // cards. Only the current round is kept in the history, so clones stay
+    // cheap.
+    fn determinize(&mut self, view: &PlayerView, rules: &Rules) -> GameState {
+        let mut unseen: Vec<Card> = (0..52)
+            .map(|i| Card::from_index(i).unwrap())
+            .filter(|c| !view.hand.cards.contains(c))
+            .collect();
+        self.rng.shuffle(&mut unseen);
+        let mut players = vec![];
+        for &(name, count) in &view.counts {
+            let hand = if name == view.player {
+                view.hand.clone()
+            } else {
+                let cards = unseen.split_off(unseen.len() - count);
+                Hand { cards }
+            };
+            players.push(Player {
+                name,
+                hand,
+                seat: Seat::Human,
+            });
+        }
+        let hands = players.iter().map(|p| (p.name, p.hand.clone())).collect();
+        let mut history = vec![Event::Deal(hands)];
+        for (player, bet) in view.round_bets() {
+            history.push(Event::Bet(player, bet));
+        }
+        GameState {
+            players,
+            current_turn: view.current_turn,
+            current_bet: view.current_bet,
+            history,
+            rules: *rules,
+            names: vec![],
+            rng: GameRng::from_seed(self.rng.next_u64()),
+        }
+    }
+
+    // Returns the moves searched for the player to act: a call, the lowest
+    // raise and the lowest raises their own cards support.
+    fn candidates(&self, state: &GameState) -> Vec<GameMove> {
+        let hand = &state.players[state.current_turn].hand;
+        let mut output = vec![];
+        if state.current_bet.is_some() {
+            output.push(GameMove::Call());
+        }
+        let mut raises = 0;
+        for hv in HandValue::ladder() {
+            if raises > self.config.width {
+                break;
+            }
+            if state.current_bet.is_some_and(|bet| hv <= bet) {
+                continue;
+            }
+            if raises == 0 || supporting_cards(&hand.cards, &hv) > 0 {
+                output.push(GameMove::Bet(hv));
+                raises += 1;
+            }
+        }
+        output
+    }
+
+    // plays the round out with random supported bets, calling at random once
+    // there is a bet and always after max_playout bets
+    fn playout(&mut self, mut state: GameState, start: usize) -> GameState {
+        let mut bets = 0;
+        while !round_over(&state, start) {
+            let moves = self.candidates(&state);
+            let call = state.current_bet.is_some() &&
+                       (bets >= self.config.max_playout || moves.len() == 1 ||
+                        self.rng.gen_range(0, 2) == 0);
+            let gm = if call {
+                GameMove::Call()
+            } else {
+                let raises = if state.current_bet.is_some() { &moves[1..] } else { &moves[..] };
+                bets += 1;
+                *self.rng.choose(raises).unwrap()
+            };
+            state = state.handle_gamemove(gm);
+        }
+        state
+    }
+
+    fn search(&mut self, view: &PlayerView, rules: &Rules) -> GameMove {
+        let mut nodes = vec![Node {
+            gm: None,
+            mover: view.player,
+            children: vec![],
+            visits: 0.0,
+            available: 0.0,
+            reward: 0.0,
+        }];
+        let started = Instant::now();
+        for _ in 0..self.config.iterations {
+            if self.config.time_limit.is_some_and(|limit| started.elapsed() >= limit) {
+                break;
+            }
+            let mut state = self.determinize(view, rules);
+            let start = state.history.len();
+            let mut path = vec![0];
+            let mut node = 0;
+            // select while every candidate has been tried, then expand one
+            while !round_over(&state, start) {
+                let moves = self.candidates(&state);
+                let mover = state.players[state.current_turn].name;
+                let untried: Vec<GameMove> = moves.iter()
+                    .filter(|&&gm| !nodes[node].children.iter().any(|&c| nodes[c].gm == Some(gm)))
+                    .cloned()
+                    .collect();
+                if !untried.is_empty() {
+                    let gm = *self.rng.choose(&untried).unwrap();
+                    let child = nodes.len();
+                    nodes.push(Node {
+                        gm: Some(gm),
+                        mover,
+                        children: vec![],
+                        visits: 0.0,
+                        available: 0.0,
+                        reward: 0.0,
+                    });
+                    nodes[node].children.push(child);
+                    for c in nodes[node].children.clone() {
+                        if nodes[c].gm.is_some_and(|m| moves.contains(&m)) {
+                            nodes[c].available += 1.0;
+                        }
+                    }
+                    state = state.handle_gamemove(gm);
+                    path.push(child);
+                    break;
+                }
+                let mut best = None;
+                let mut best_score = f64::NEG_INFINITY;
+                for c in nodes[node].children.clone() {
+                    if !nodes[c].gm.is_some_and(|m| moves.contains(&m)) {
+                        continue;
+                    }
+                    nodes[c].available += 1.0;
+                    let n = &nodes[c];
+                    let score = n.reward / n.visits +
+                                self.config.exploration * (n.available.ln() / n.visits).sqrt();
+                    if score > best_score {
+                        best_score = score;
+                        best = Some(c);
+                    }
+                }
+                node = best.unwrap();
+                state = state.handle_gamemove(nodes[node].gm.unwrap());
+                path.push(node);
+            }
+            let state = self.playout(state, start);
+            let loser = round_loser(&state, start);
+            for &n in &path {
+                nodes[n].visits += 1.0;
+                if Some(nodes[n].mover) != loser {
+                    nodes[n].reward += 1.0;
+                }
+            }
+        }
+        let best = nodes[0].children
+            .iter()
+            .max_by(|&&a, &&b| nodes[a].visits.partial_cmp(&nodes[b].visits).unwrap());
+        match best {
+            Some(&c) => nodes[c].gm.unwrap(),
+            None => view.legal_moves()[0],
+        }
+    }
+}
+
+impl Strategy for IsmctsBot {
+    fn name(&self) -> String {
+        "ismcts".to_owned()
+    }
+
+    fn choose(&mut self, view: &PlayerView, rules: &Rules) -> GameMove {
+        self.search(view, rules)
+    }
+
+    fn new_game(&mut self, seed: u64) {
+        self.rng = GameRng::from_seed(seed);
+    }
+}
+
+// returns true once the round that started with the history at `start` has
+// been called
+fn round_over(state: &GameState, start: usize) -> bool {
+    state.history[start..].iter().any(|e| matches!(*e, Event::Call { .. }))
+}
+
+// returns the player who lost the call that ended the round
+fn round_loser(state: &GameState, start: usize) -> Option<u8> {
+    state.history[start..].iter().filter_map(|e| match *e {
+        Event::Call { caller, bettor, present, .. } => {
+            Some(if present { caller } else { bettor })
+        }
+        _ => None,
+    }).next()
+}
+
+#[cfg(test)]
+mod test {
+    use card::{Card, Suit, Rank};
+    use game::{GameMove, PlayerView, Rules};
+    use poker::{Hand, HandValue};
+    use strategy::Strategy;
+    use strategy::ismcts::{IsmctsBot, IsmctsConfig};
+
+    #[test]
+    fn test_ismcts_calls_impossible_bet() {
+        let hand = Hand {
+            cards: vec![Card {
+                rank: Rank::Ace,
+                suit: Suit::Clubs,
+            }],
+        };
+        // a pair of kings among two cards is impossible when one is an ace
+        let view = PlayerView {
+            player: 0,
+            hand,
+            counts: vec![(0, 1), (1, 1)],
+            current_turn: 0,
+            current_bet: Some(HandValue::OnePair(Rank::King)),
+            history: vec![],
+        };
+        let config = IsmctsConfig {
+            iterations: 500,
+            ..IsmctsConfig::default()
+        };
+        let mut bot = IsmctsBot::new(config, 7);
+        assert_eq!(bot.choose(&view, &Rules::default()), GameMove::Call());
+    }
+}
@@ -0,0 +1,323 @@
+pub mod bluff;
+pub mod cfr;
+pub mod external;
+pub mod ismcts;
+pub mod threshold;
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::Rng;
+
+use game::{GameMove, PlayerView, Rules, Seat};
+use nn::{Mlp, NnBot};
+use rng::GameRng;
+use strategy::bluff::{BluffBot, BluffConfig};
+use strategy::cfr::{CfrBot, CfrConfig};
+use strategy::external::{ExternalBot, ExternalConfig};
+use strategy::ismcts::{IsmctsBot, IsmctsConfig};
+use strategy::threshold::{Difficulty, ThresholdBot};
+
+// Chooses moves for a computer player from what that player can see
+pub trait Strategy {
+    // short name used when announcing moves and in reports
+    fn name(&self) -> String;
+
+    // returns the move to make. It is always the view's player's turn, and
+    // the move must be one of view.legal_moves().
+    fn choose(&mut self, view: &PlayerView, rules: &Rules) -> GameMove;
+
+    // Readies the player for another game, so that one costly to make, e.g.
+    // trained or running as a program, can play many. Random choices start
+    // over from the seed and what it learned about the last game is dropped.
+    fn new_game(&mut self, _seed: u64) {}
+}
+
+// The built-in computer players a seat can be given
+#[derive(Clone, Debug, PartialEq)]
+pub enum BotKind {
+    Random,
+    Caller,
+    MinRaise,
+    Threshold(ThresholdBot),
+    Bluff(BluffConfig),
+    // plays a CFR strategy trained for two players with this many cards
+    Cfr { hand_size: usize, iterations: usize },
+    Ismcts(IsmctsConfig),
+    // plays by the network in the weights file
+    Nn(String),
+    // asks a separate program for its moves
+    External(ExternalConfig),
+}
+
+impl FromStr for BotKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BotKind, String> {
+        BotKind::parse(s).ok_or_else(|| format!("unknown bot: {}", s))
+    }
+}
+
+impl BotKind {
+    // parses a bot name. Threshold bots are given by difficulty ("easy",
+    // "medium", "hard") or by thresholds ("threshold:0.3:0.5"), bluffing bots
+    // by aggression and bluff rate ("bluff:0.3:0.2", optionally followed by
+    // call_below, raise_above and trust; all but trust are between 0 and 1),
+    // CFR bots by hand size and training iterations ("cfr:1:200") and search
+    // bots by iterations or thinking time ("ismcts:2000", "ismcts:500ms").
+    // Network bots name their weights file ("nn:weights.txt"), which must
+    // load. External bots give the command that starts them
+    // ("ext:python3 mybot.py").
+    fn parse(s: &str) -> Option<BotKind> {
+        if let Ok(difficulty) = s.parse() {
+            return Some(BotKind::Threshold(ThresholdBot::preset(difficulty)));
+        }
+        let vec: Vec<&str> = s.split(':').collect();
+        match vec[0] {
+            "random" => Some(BotKind::Random),
+            "call" => Some(BotKind::Caller),
+            "minraise" => Some(BotKind::MinRaise),
+            "bluff" if vec.len() == 1 => Some(BotKind::Bluff(BluffConfig::default())),
+            "bluff" if vec.len() == 3 || vec.len() == 6 => {
+                let numbers: Vec<f64> = vec[1..].iter().filter_map(|s| s.parse().ok()).collect();
+                if numbers.len() != vec.len() - 1 {
+                    return None;
+                }
+                let mut config = BluffConfig {
+                    aggression: numbers[0],
+                    bluff: numbers[1],
+                    ..BluffConfig::default()
+                };
+                if numbers.len() == 5 {
+                    config.call_below = numbers[2];
+                    config.raise_above = numbers[3];
+                    config.trust = numbers[4];
+                }
+                let rates = [config.aggression, config.bluff, config.call_below, config.raise_above];
+                if rates.iter().any(|r| !(0.0..=1.0).contains(r)) || config.trust < 0.0 {
+                    return None;
+                }
+                Some(BotKind::Bluff(config))
+            }
+            "cfr" if vec.len() <= 3 => {
+                let hand_size = vec.get(1).map_or(Ok(1), |s| s.parse());
+                // training with two cards each takes about a second per iteration
+                let default = if vec.get(1) == Some(&"2") { 20 } else { 200 };
+                let iterations = vec.get(2).map_or(Ok(default), |s| s.parse());
+                match (hand_size, iterations) {
+                    (Ok(hand_size), Ok(iterations)) if hand_size == 1 || hand_size == 2 => {
+                        Some(BotKind::Cfr { hand_size, iterations })
+                    }
+                    _ => None,
+                }
+            }
+            "ismcts" if vec.len() == 1 => Some(BotKind::Ismcts(IsmctsConfig::default())),
+            "ismcts" if vec.len() == 2 => {
+                let mut config = IsmctsConfig::default();
+                if vec[1].ends_with("ms") {
+                    let ms = vec[1].trim_end_matches("ms").parse().ok()?;
+                    config.iterations = usize::MAX;
+                    config.time_limit = Some(Duration::from_millis(ms));
+                } else {
+                    config.iterations = vec[1].parse().ok()?;
+                }
+                Some(BotKind::Ismcts(config))
+            }
+            "ext" if vec.len() >= 2 => {
+                let config = ExternalConfig::new(&s["ext:".len()..]);
+                if config.command.is_empty() {
+                    return None;
+                }
+                Some(BotKind::External(config))
+            }
+            "nn" if vec.len() >= 2 => {
+                // the file name may itself contain colons
+                let path = s["nn:".len()..].to_owned();
+                match Mlp::load(Path::new(&path)) {
+                    Ok(ref net) if net.players().is_some() => Some(BotKind::Nn(path)),
+                    _ => None,
+                }
+            }
+            "threshold" if vec.len() == 1 => {
+                Some(BotKind::Threshold(ThresholdBot::preset(Difficulty::Medium)))
+            }
+            "threshold" if vec.len() == 3 => {
+                match (vec[1].parse(), vec[2].parse()) {
+                    (Ok(call_below), Ok(raise_above)) => {
+                        Some(BotKind::Threshold(ThresholdBot::new(call_below, raise_above)))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // returns the seat of a player of this kind
+    pub fn seat(&self) -> Seat {
+        Seat::Bot(self.to_string())
+    }
+
+    // Returns a new player for the bot seat. Seats are checked when they are
+    // given, so this only fails if the name is of a file that has changed
+    // since, e.g. network weights that no longer load.
+    pub fn build_seat(name: &str, seed: u64) -> Result<Box<dyn Strategy>, String> {
+        Ok(name.parse::<BotKind>()?.build(seed))
+    }
+
+    // returns a new player of this kind. Randomized players draw from the seed.
+    pub fn build(&self, seed: u64) -> Box<dyn Strategy> {
+        match *self {
+            BotKind::Random => Box::new(RandomBot::new(seed)),
+            BotKind::Caller => Box::new(CallBot),
+            BotKind::MinRaise => Box::new(MinRaiseBot),
+            BotKind::Threshold(bot) => Box::new(bot),
+            BotKind::Bluff(config) => Box::new(BluffBot::new(config, seed)),
+            BotKind::Cfr { hand_size, iterations } => {
+                let config = CfrConfig { hand_size, ..CfrConfig::default() };
+                Box::new(CfrBot::train(config, iterations, seed))
+            }
+            BotKind::Ismcts(config) => Box::new(IsmctsBot::new(config, seed)),
+            BotKind::Nn(ref path) => {
+                // from_str checked that the file loads; if it has changed
+                // since, play randomly rather than stop the game
+                match Mlp::load(Path::new(path)).ok().and_then(|net| NnBot::new(net, false, seed)) {
+                    Some(bot) => Box::new(bot),
+                    None => Box::new(RandomBot::new(seed)),
+                }
+            }
+            BotKind::External(ref config) => Box::new(ExternalBot::new(config.clone())),
+        }
+    }
+}
+
+impl fmt::Display for BotKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BotKind::Random => write!(f, "random"),
+            BotKind::Caller => write!(f, "call"),
+            BotKind::MinRaise => write!(f, "minraise"),
+            BotKind::Threshold(bot) => {
+                write!(f, "threshold:{}:{}", bot.call_below, bot.raise_above)
+            }
+            BotKind::Bluff(config) => {
+                let default = BluffConfig::default();
+                if (config.call_below, config.raise_above, config.trust) ==
+                   (default.call_below, default.raise_above, default.trust) {
+                    write!(f, "bluff:{}:{}", config.aggression, config.bluff)
+                } else {
+                    write!(f,
+                           "bluff:{}:{}:{}:{}:{}",
+                           config.aggression,
+                           config.bluff,
+                           config.call_below,
+                           config.raise_above,
+                           config.trust)
+                }
+            }
+            BotKind::Cfr { hand_size, iterations } => write!(f, "cfr:{}:{}", hand_size, iterations),
+            BotKind::Ismcts(config) => {
+                match config.time_limit {
+                    Some(limit) => write!(f, "ismcts:{}ms", limit.as_millis()),
+                    None => write!(f, "ismcts:{}", config.iterations),
+                }
+            }
+            BotKind::Nn(ref path) => write!(f, "nn:{}", path),
+            BotKind::External(ref config) => write!(f, "ext:{}", config.command.join(" ")),
+        }
+    }
+}
+
+// Picks uniformly among the legal moves
+#[derive(Clone, Debug)]
+pub struct RandomBot {
+    rng: GameRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> RandomBot {
+        RandomBot { rng: GameRng::from_seed(seed) }
+    }
+}
+
+impl Strategy for RandomBot {
+    fn name(&self) -> String {
+        "random".to_owned()
+    }
+
+    fn choose(&mut self, view: &PlayerView, _rules: &Rules) -> GameMove {
+        let moves = view.legal_moves();
+        *self.rng.choose(&moves).unwrap()
+    }
+
+    fn new_game(&mut self, seed: u64) {
+        self.rng = GameRng::from_seed(seed);
+    }
+}
+
+// Calls every bet, and opens with the lowest bet
+#[derive(Clone, Copy, Debug)]
+pub struct CallBot;
+
+impl Strategy for CallBot {
+    fn name(&self) -> String {
+        "call".to_owned()
+    }
+
+    fn choose(&mut self, view: &PlayerView, _rules: &Rules) -> GameMove {
+        match view.current_bet {
+            Some(_) => GameMove::Call(),
+            None => GameMove::Bet(view.min_raise().unwrap()),
+        }
+    }
+}
+
+// Always raises to the lowest bet above the current one, and calls once
+// there is nothing left to raise to
+#[derive(Clone, Copy, Debug)]
+pub struct MinRaiseBot;
+
+impl Strategy for MinRaiseBot {
+    fn name(&self) -> String {
+        "minraise".to_owned()
+    }
+
+    fn choose(&mut self, view: &PlayerView, _rules: &Rules) -> GameMove {
+        match view.min_raise() {
+            Some(hv) => GameMove::Bet(hv),
+            None => GameMove::Call(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use game::{Event, GameState, Rules};
+    use strategy::{BotKind, Strategy};
+
+    #[test]
+    fn test_bots_play_legal_moves_to_the_end() {
+        let kinds = [BotKind::Random, BotKind::Caller, BotKind::MinRaise];
+        let mut bots: Vec<Box<dyn Strategy>> = kinds.iter().map(|k| k.build(5)).collect();
+        let mut state = GameState::seeded(3, 3, Rules::default(), 11).unwrap();
+        let mut moves = 0;
+        while state.winner().is_none() {
+            let view = state.view(state.current_turn);
+            let name = view.player as usize;
+            let gm = bots[name].choose(&view, &state.rules);
+            assert!(view.legal_moves().contains(&gm), "{:?}", gm);
+            state = state.handle_gamemove(gm);
+            moves += 1;
+            assert!(moves < 10_000);
+        }
+        // every call costs exactly one of the 9 cards dealt
+        let calls = state
+            .history
+            .iter()
+            .filter(|e| matches!(**e, Event::Call { .. }))
+            .count();
+        assert_eq!(state.players[0].hand.cards.len(), 9 - calls);
+    }
+}
@@ -0,0 +1,125 @@
+use std::str::FromStr;
+
+use game::{GameMove, PlayerView, Rules};
+use logic;
+use strategy::Strategy;
+
+// How hard a ThresholdBot is to beat
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Difficulty, String> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty: {} (easy, medium or hard)", s)),
+        }
+    }
+}
+
+// Calls when the current bet is unlikely to be present given its own cards,
+// and otherwise raises to the lowest bet that is likely enough
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThresholdBot {
+    // call when the current bet is present with less than this probability
+    pub call_below: f64,
+    // only raise to bets present with at least this probability
+    pub raise_above: f64,
+}
+
+impl ThresholdBot {
+    pub fn new(call_below: f64, raise_above: f64) -> ThresholdBot {
+        ThresholdBot {
+            call_below,
+            raise_above,
+        }
+    }
+
+    // Easy calls too rarely and raises to risky bets; hard calls anything
+    // that is more likely missing than present and only makes safe raises.
+    pub fn preset(difficulty: Difficulty) -> ThresholdBot {
+        match difficulty {
+            Difficulty::Easy => ThresholdBot::new(0.15, 0.25),
+            Difficulty::Medium => ThresholdBot::new(0.35, 0.5),
+            Difficulty::Hard => ThresholdBot::new(0.5, 0.65),
+        }
+    }
+}
+
+impl Strategy for ThresholdBot {
+    fn name(&self) -> String {
+        format!("threshold({},{})", self.call_below, self.raise_above)
+    }
+
+    fn choose(&mut self, view: &PlayerView, _rules: &Rules) -> GameMove {
+        let total = view.total_cards();
+        if let Some(bet) = view.current_bet {
+            if logic::handvalue_prob(&bet, &view.hand, total) < self.call_below {
+                return GameMove::Call();
+            }
+        }
+        for gm in view.legal_moves() {
+            if let GameMove::Bet(hv) = gm {
+                if logic::handvalue_prob(&hv, &view.hand, total) >= self.raise_above {
+                    return gm;
+                }
+            }
+        }
+        // nothing is likely enough, so call or open as low as possible
+        match view.current_bet {
+            Some(_) => GameMove::Call(),
+            None => GameMove::Bet(view.min_raise().unwrap()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use card::{Card, Rank, Suit};
+    use game::{GameMove, PlayerView, Rules};
+    use poker::{Hand, HandValue};
+    use strategy::Strategy;
+    use strategy::threshold::{Difficulty, ThresholdBot};
+
+    #[test]
+    fn test_threshold_bot() {
+        let mut view = PlayerView {
+            player: 0,
+            hand: Hand {
+                cards: vec![
+                    Card {
+                        rank: Rank::Nine,
+                        suit: Suit::Hearts,
+                    },
+                    Card {
+                        rank: Rank::Eight,
+                        suit: Suit::Clubs,
+                    },
+                ],
+            },
+            counts: vec![(0, 2), (1, 1)],
+            current_turn: 0,
+            current_bet: Some(HandValue::OnePair(Rank::Ace)),
+            history: vec![],
+        };
+        let rules = Rules::default();
+        let mut bot = ThresholdBot::preset(Difficulty::Medium);
+        assert_eq!(bot.choose(&view, &rules), GameMove::Call());
+        // its own cards are certainly there, but nothing else is likely
+        view.current_bet = Some(HandValue::HighCard(Rank::Eight));
+        assert_eq!(
+            bot.choose(&view, &rules),
+            GameMove::Bet(HandValue::HighCard(Rank::Nine))
+        );
+        view.current_bet = Some(HandValue::HighCard(Rank::Nine));
+        assert_eq!(bot.choose(&view, &rules), GameMove::Call());
+    }
+}
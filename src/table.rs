@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use card::Card;
+use logic;
+use poker::{Hand, HandValue};
+
+// Table files start with these bytes followed by the format version
+pub const TABLE_MAGIC: &[u8; 4] = b"BPPT";
+pub const TABLE_VERSION: u16 = 1;
+
+// Probabilities are stored as fractions of this value
+const SCALE: f64 = 65535.0;
+
+// Precomputed probability of every bet on the HandValue ladder for a set of
+// (own hand, total cards) pairs. Probabilities are stored in 16 bits, so a
+// lookup is within 1/65535 of the exact value.
+#[derive(Clone, Debug, Default)]
+pub struct ProbTable {
+    // (hand mask, total cards) -> position of the row in `probs`
+    rows: HashMap<(u64, u8), usize>,
+    probs: Vec<u16>,
+}
+
+impl ProbTable {
+    // computes the probability of every bet for every pair of the input hands
+    // and total card counts
+    pub fn generate(hands: &[Hand], totals: &[u8]) -> ProbTable {
+        let ladder = HandValue::ladder();
+        let mut table = ProbTable::default();
+        for hand in hands {
+            for &total in totals {
+                if (total as usize) < hand.cards.len() {
+                    continue;
+                }
+                let row: Vec<u16> = ladder
+                    .iter()
+                    .map(|hv| (logic::handvalue_prob(hv, hand, total) * SCALE).round() as u16)
+                    .collect();
+                table.insert_row(hand.to_mask(), total, &row);
+            }
+        }
+        table
+    }
+
+    // returns every hand with the input number of cards
+    pub fn all_hands(size: usize) -> Vec<Hand> {
+        let mut output = vec![];
+        let mut cards = vec![];
+        push_hands(&mut output, &mut cards, 0, size);
+        output
+    }
+
+    fn insert_row(&mut self, mask: u64, total: u8, row: &[u16]) {
+        let start = self.probs.len();
+        self.probs.extend_from_slice(row);
+        self.rows.insert((mask, total), start);
+    }
+
+    // returns the number of (hand, total) pairs in the table
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    // returns the stored probability, or None if the pair is not in the table
+    pub fn get(&self, hv: &HandValue, hand: &Hand, total: u8) -> Option<f64> {
+        let index = hv.ladder_index()?;
+        self.rows
+            .get(&(hand.to_mask(), total))
+            .map(|start| self.probs[start + index] as f64 / SCALE)
+    }
+
+    // returns the stored probability, computing it when the pair is missing
+    pub fn prob(&self, hv: &HandValue, hand: &Hand, total: u8) -> f64 {
+        match self.get(hv, hand, total) {
+            Some(p) => p,
+            None => logic::handvalue_prob(hv, hand, total),
+        }
+    }
+
+    // Writes the table in the versioned binary format:
+    //   magic, version (u16), ladder length (u16), row count (u32), then per
+    //   row the hand mask (u64), the total (u8) and one u16 per bet.
+    // Numbers are little endian.
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let ladder_len = HandValue::ladder().len();
+        out.write_all(TABLE_MAGIC)?;
+        out.write_all(&TABLE_VERSION.to_le_bytes())?;
+        out.write_all(&(ladder_len as u16).to_le_bytes())?;
+        out.write_all(&(self.rows.len() as u32).to_le_bytes())?;
+        // sorted so the same table always produces the same file
+        let mut keys: Vec<&(u64, u8)> = self.rows.keys().collect();
+        keys.sort();
+        for key in keys {
+            let start = self.rows[key];
+            out.write_all(&key.0.to_le_bytes())?;
+            out.write_all(&[key.1])?;
+            for p in &self.probs[start..start + ladder_len] {
+                out.write_all(&p.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(input: &mut R) -> io::Result<ProbTable> {
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != TABLE_MAGIC {
+            return Err(invalid("not a probability table"));
+        }
+        let version = read_u16(input)?;
+        if version != TABLE_VERSION {
+            return Err(invalid(&format!(
+                "unsupported table version {} (expected {})",
+                version,
+                TABLE_VERSION
+            )));
+        }
+        let ladder_len = read_u16(input)? as usize;
+        if ladder_len != HandValue::ladder().len() {
+            return Err(invalid("table was built for a different bet ladder"));
+        }
+        let mut count = [0u8; 4];
+        input.read_exact(&mut count)?;
+        let count = u32::from_le_bytes(count);
+        let mut table = ProbTable::default();
+        let mut row = vec![0u16; ladder_len];
+        for read in 0..count {
+            let mut mask = [0u8; 8];
+            let mut total = [0u8; 1];
+            let result = input.read_exact(&mut mask)
+                .and_then(|_| input.read_exact(&mut total))
+                .and_then(|_| {
+                    for p in row.iter_mut() {
+                        *p = read_u16(input)?;
+                    }
+                    Ok(())
+                });
+            if let Err(error) = result {
+                if error.kind() == io::ErrorKind::UnexpectedEof {
+                    return Err(invalid(&format!("table has {} rows, its header says {}", read, count)));
+                }
+                return Err(error);
+            }
+            table.insert_row(u64::from_le_bytes(mask), total[0], &row);
+        }
+        if table.len() != count as usize {
+            return Err(invalid("table has the same row more than once"));
+        }
+        if input.read(&mut [0u8; 1])? != 0 {
+            return Err(invalid(&format!("table has more rows than the {} its header says", count)));
+        }
+        Ok(table)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<ProbTable> {
+        ProbTable::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+// A table file that is only read the first time a probability is needed.
+// If the file cannot be read every probability is computed live.
+#[derive(Debug)]
+pub struct LazyTable {
+    path: PathBuf,
+    table: Option<ProbTable>,
+}
+
+impl LazyTable {
+    pub fn new<P: Into<PathBuf>>(path: P) -> LazyTable {
+        LazyTable {
+            path: path.into(),
+            table: None,
+        }
+    }
+
+    pub fn prob(&mut self, hv: &HandValue, hand: &Hand, total: u8) -> f64 {
+        if self.table.is_none() {
+            self.table = Some(ProbTable::load(&self.path).unwrap_or_default());
+        }
+        self.table.as_ref().unwrap().prob(hv, hand, total)
+    }
+}
+
+fn push_hands(output: &mut Vec<Hand>, cards: &mut Vec<Card>, next: u8, size: usize) {
+    if cards.len() == size {
+        output.push(Hand { cards: cards.clone() });
+        return;
+    }
+    for index in next..52 {
+        cards.push(Card::from_index(index).unwrap());
+        push_hands(output, cards, index + 1, size);
+        cards.pop();
+    }
+}
+
+fn read_u16<R: Read>(input: &mut R) -> io::Result<u16> {
+    let mut bytes = [0u8; 2];
+    input.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use card::{Card, Suit, Rank};
+    use logic::handvalue_prob;
+    use poker::{Hand, HandValue};
+    use table::ProbTable;
+
+    #[test]
+    fn test_table_round_trip() {
+        let hands = ProbTable::all_hands(1);
+        assert_eq!(hands.len(), 52);
+        let table = ProbTable::generate(&hands[..4], &[3, 10]);
+        assert_eq!(table.len(), 8);
+        let mut bytes = vec![];
+        table.write_to(&mut bytes).unwrap();
+        let loaded = ProbTable::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(loaded.len(), 8);
+        for hand in &hands[..4] {
+            for hv in &HandValue::ladder() {
+                let exact = handvalue_prob(hv, hand, 10);
+                let stored = loaded.get(hv, hand, 10).unwrap();
+                assert!((exact - stored).abs() <= 1.0 / 65535.0);
+            }
+        }
+        // a row missing or left over
+        let row = (bytes.len() - 12) / 8;
+        assert!(ProbTable::read_from(&mut &bytes[..bytes.len() - row]).is_err());
+        let mut longer = bytes.clone();
+        longer.extend_from_slice(&bytes[12..12 + row]);
+        assert!(ProbTable::read_from(&mut &longer[..]).is_err());
+        // corrupt the version
+        bytes[4] = 99;
+        assert!(ProbTable::read_from(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_table_falls_back_to_live() {
+        let table = ProbTable::default();
+        let hand = Hand {
+            cards: vec![Card {
+                rank: Rank::King,
+                suit: Suit::Clubs,
+            }],
+        };
+        let hv = HandValue::OnePair(Rank::King);
+        assert_eq!(table.get(&hv, &hand, 20), None);
+        assert_eq!(table.prob(&hv, &hand, 20), handvalue_prob(&hv, &hand, 20));
+    }
+}
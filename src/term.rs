@@ -0,0 +1,128 @@
+use std::env;
+use std::fs::File;
+use std::process::{Command, Stdio};
+
+// Width used when the terminal size cannot be determined
+pub const DEFAULT_WIDTH: usize = 80;
+
+// runs stty on the controlling terminal and returns its output, if there is
+// a terminal
+fn stty(args: &[&str]) -> Option<String> {
+    let tty = match File::open("/dev/tty") {
+        Ok(tty) => tty,
+        Err(_) => return None,
+    };
+    let output = match Command::new("stty")
+        .args(args)
+        .stdin(Stdio::from(tty))
+        .stderr(Stdio::null())
+        .output() {
+        Ok(output) => output,
+        Err(_) => return None,
+    };
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// returns the (columns, rows) of the controlling terminal, if there is one
+pub fn size() -> Option<(usize, usize)> {
+    let text = stty(&["size"])?;
+    let vec: Vec<&str> = text.split_whitespace().collect();
+    if vec.len() != 2 {
+        return None;
+    }
+    match (vec[0].parse(), vec[1].parse()) {
+        (Ok(rows), Ok(cols)) if cols > 0 => Some((cols, rows)),
+        _ => None,
+    }
+}
+
+// Puts the terminal in raw mode, where keys are read as they are pressed and
+// not echoed, until dropped
+pub struct RawMode {
+    // the settings to restore, as printed by stty -g
+    saved: String,
+}
+
+impl RawMode {
+    pub fn enter() -> Option<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        Some(RawMode { saved: saved.trim().to_owned() })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+// Returns the number of columns available for output. $COLUMNS wins over
+// the size reported by the terminal so that the width can be forced. Asking
+// the terminal runs stty, so call this once per screen drawn, not per line;
+// it is not remembered, so that output follows the terminal when resized.
+pub fn width() -> usize {
+    if let Ok(columns) = env::var("COLUMNS") {
+        if let Ok(columns) = columns.trim().parse() {
+            if columns > 0 {
+                return columns;
+            }
+        }
+    }
+    match size() {
+        Some((cols, _)) => cols,
+        None => DEFAULT_WIDTH,
+    }
+}
+
+// returns the number of columns the string occupies on screen, skipping
+// ANSI escape sequences
+pub fn visible_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut in_escape = false;
+    for ch in s.chars() {
+        if in_escape {
+            if ch.is_ascii_alphabetic() {
+                in_escape = false;
+            }
+        } else if ch == '\x1b' {
+            in_escape = true;
+        } else {
+            len += 1;
+        }
+    }
+    len
+}
+
+// returns the string cut or padded with spaces to exactly the input number of
+// visible columns
+pub fn fit(s: &str, width: usize) -> String {
+    let mut output = String::new();
+    let mut len = 0;
+    let mut in_escape = false;
+    let mut colored = false;
+    for ch in s.chars() {
+        if in_escape {
+            if ch.is_ascii_alphabetic() {
+                in_escape = false;
+            }
+        } else if ch == '\x1b' {
+            in_escape = true;
+            colored = true;
+        } else if len == width {
+            // stop any color that was cut off
+            if colored {
+                output.push_str("\x1b[0m");
+            }
+            return output;
+        } else {
+            len += 1;
+        }
+        output.push(ch);
+    }
+    output.extend((len..width).map(|_| ' '));
+    output
+}
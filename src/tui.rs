@@ -0,0 +1,581 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use card::RenderOptions;
+use game::{Event, GameMove, GameState, Seat};
+use poker::HandValue;
+use reveal::Reveal;
+use rng::GameRng;
+use strategy::{BotKind, Strategy};
+use term;
+
+// How long a bot's move stays on screen before the next move
+const BOT_DELAY: Duration = Duration::from_millis(700);
+// How often the terminal size is checked. Asking runs stty, so it is not
+// done for every key.
+const RESIZE_POLL: Duration = Duration::from_secs(1);
+
+// A key pressed in the terminal
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Enter,
+    Escape,
+    Char(char),
+    // a key the UI has no use for
+    Other,
+}
+
+impl Key {
+    // splits the bytes read from a terminal in raw mode into keys
+    pub fn parse(bytes: &[u8]) -> Vec<Key> {
+        let mut keys = vec![];
+        let mut i = 0;
+        while i < bytes.len() {
+            let (key, len) = match bytes[i] {
+                b'\x1b' => escape(&bytes[i + 1..]),
+                b'\r' | b'\n' => (Key::Enter, 1),
+                b if b < 0x80 => (Key::Char(b as char), 1),
+                _ => (Key::Other, 1),
+            };
+            keys.push(key);
+            i += len;
+        }
+        keys
+    }
+}
+
+// returns the key of the escape sequence starting after the escape byte, and
+// the length of the sequence including it
+fn escape(rest: &[u8]) -> (Key, usize) {
+    match rest {
+        [b'[', b'A', ..] | [b'O', b'A', ..] => (Key::Up, 3),
+        [b'[', b'B', ..] | [b'O', b'B', ..] => (Key::Down, 3),
+        [b'[', b'C', ..] | [b'O', b'C', ..] => (Key::Right, 3),
+        [b'[', b'D', ..] | [b'O', b'D', ..] => (Key::Left, 3),
+        [b'[', b'5', b'~', ..] => (Key::PageUp, 4),
+        [b'[', b'6', b'~', ..] => (Key::PageDown, 4),
+        [b'[', ..] => {
+            // skip any other sequence up to its final byte
+            match rest[1..].iter().position(|b| b.is_ascii_alphabetic() || *b == b'~') {
+                Some(end) => (Key::Other, end + 3),
+                None => (Key::Other, rest.len() + 1),
+            }
+        }
+        _ => (Key::Escape, 1),
+    }
+}
+
+// What the player asked for with a key
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Move(GameMove),
+    Quit,
+}
+
+// The state of the full-screen UI: the screen size, the log of the game, the
+// bet being picked and which human's hand is shown. Rendering only depends on
+// this and the game, so the UI can be drawn to a buffer without a terminal.
+pub struct Tui {
+    pub width: usize,
+    pub height: usize,
+    // lines of the event log, oldest first
+    pub log: Vec<String>,
+    // how many lines the log is scrolled back from the newest
+    pub scroll: usize,
+    // index in the ladder of the bet shown in the picker
+    pub pick: usize,
+    // the player whose hand is shown
+    pub viewer: u8,
+    // feedback about the last key, e.g. why a bet was refused
+    pub message: String,
+    // how to draw cards; hands are boxed when the screen has room
+    pub cards: RenderOptions,
+    ladder: Vec<HandValue>,
+    // history events already in the log
+    logged: usize,
+    bots: HashMap<u8, Box<dyn Strategy>>,
+    // seeds the bots
+    rng: GameRng,
+}
+
+impl Tui {
+    pub fn new(state: &GameState, width: usize, height: usize, seed: u64) -> Tui {
+        let viewer = state.players
+            .iter()
+            .find(|p| p.seat == Seat::Human)
+            .unwrap_or(&state.players[state.current_turn])
+            .name;
+        let mut tui = Tui {
+            width,
+            height,
+            log: vec![],
+            scroll: 0,
+            pick: 0,
+            viewer,
+            message: String::new(),
+            cards: RenderOptions::default(),
+            ladder: HandValue::ladder(),
+            logged: 0,
+            bots: HashMap::new(),
+            rng: GameRng::from_seed(seed),
+        };
+        tui.update(state);
+        tui
+    }
+
+    // returns true iff a bot makes the next move
+    pub fn bot_to_move(&self, state: &GameState) -> bool {
+        state.winner().is_none() &&
+        match state.players[state.current_turn].seat {
+            Seat::Bot(_) => true,
+            Seat::Human => false,
+        }
+    }
+
+    // Catches up with the game after a move: logs the new events, shows the
+    // hand of the human to move and keeps the picked bet legal
+    pub fn update(&mut self, state: &GameState) {
+        if self.logged > state.history.len() {
+            // a new game started over the history
+            self.logged = 0;
+            self.log.push("New game".to_owned());
+        }
+        for i in self.logged..state.history.len() {
+            let lines = self.event_lines(state, i);
+            self.log.extend(lines);
+        }
+        self.logged = state.history.len();
+        // new lines are only seen at the bottom of the log
+        self.scroll = 0;
+        if let Some(winner) = state.winner() {
+            self.message = format!("{} wins! Press q to quit", state.player_name(winner));
+            return;
+        }
+        let player = &state.players[state.current_turn];
+        if player.seat == Seat::Human {
+            self.viewer = player.name;
+        }
+        // the picker starts at the lowest raise
+        self.pick = self.lowest_bet(state);
+    }
+
+    // makes the move for the player whose turn it is
+    pub fn play(&mut self, state: GameState, gm: GameMove) -> GameState {
+        let state = state.handle_gamemove(gm);
+        self.update(&state);
+        state
+    }
+
+    // returns the ladder index of the lowest legal bet
+    fn lowest_bet(&self, state: &GameState) -> usize {
+        state.view(state.current_turn)
+            .min_raise()
+            .and_then(|hv| hv.ladder_index())
+            .unwrap_or(self.ladder.len() - 1)
+    }
+
+    // returns the log lines of the input history event
+    fn event_lines(&self, state: &GameState, i: usize) -> Vec<String> {
+        let compact = RenderOptions {
+            compact: true,
+            ..self.cards
+        };
+        match state.history[i] {
+            Event::Deal(ref hands) => {
+                let counts: Vec<String> = hands.iter()
+                    .map(|&(name, ref hand)| {
+                        format!("{} {}", state.player_name(name), hand.cards.len())
+                    })
+                    .collect();
+                vec![format!("New round, cards: {}", counts.join(", "))]
+            }
+            Event::Bet(player, bet) => {
+                vec![format!("{} bets {}", state.player_name(player), bet.to_text())]
+            }
+            Event::Call { .. } => {
+                // the hands of the round are public once it is called
+                match Reveal::at(&state.history, i, &state.rules) {
+                    Some(reveal) => reveal.report(state, &compact).lines().map(|s| s.to_owned()).collect(),
+                    None => vec![],
+                }
+            }
+        }
+    }
+
+    // Handles a key, returning the move or quit it asks for. Keys that only
+    // change the UI return None.
+    pub fn press(&mut self, key: Key, state: &GameState) -> Option<Action> {
+        self.message.clear();
+        match key {
+            Key::Char('q') | Key::Char('\u{3}') => return Some(Action::Quit),
+            Key::PageUp => {
+                let rows = self.log_rows(state);
+                self.scroll = (self.scroll + rows).min(self.log.len().saturating_sub(rows));
+            }
+            Key::PageDown => self.scroll = self.scroll.saturating_sub(self.log_rows(state)),
+            _ if state.winner().is_some() || self.bot_to_move(state) => {}
+            Key::Up => self.pick = (self.pick + 1).min(self.ladder.len() - 1),
+            Key::Down => self.pick = self.pick.saturating_sub(1).max(self.lowest_bet(state)),
+            Key::Right => {
+                // the lowest bet of the next kind of hand
+                let pick = self.ladder[self.pick];
+                if let Some(i) = self.ladder[self.pick..].iter().position(|hv| !same_kind(hv, &pick)) {
+                    self.pick += i;
+                }
+            }
+            Key::Left => {
+                // the lowest bet of this kind of hand, or of the kind before
+                // if the pick already is the lowest
+                let lowest = self.lowest_bet(state);
+                let ladder = &self.ladder;
+                let mut i = self.pick;
+                if i > lowest && !same_kind(&ladder[i - 1], &ladder[i]) {
+                    i -= 1;
+                }
+                while i > lowest && same_kind(&ladder[i - 1], &ladder[i]) {
+                    i -= 1;
+                }
+                self.pick = i;
+            }
+            Key::Enter => {
+                // once the top of the ladder is bet, the picker rests on the
+                // current bet, which cannot be bet again
+                let gm = GameMove::Bet(self.ladder[self.pick]);
+                if state.view(state.current_turn).legal_moves().contains(&gm) {
+                    return Some(Action::Move(gm));
+                }
+                self.message = "No bet is higher than the current bet; press c to call".to_owned();
+            }
+            Key::Char('c') => {
+                if state.current_bet.is_some() {
+                    return Some(Action::Move(GameMove::Call()));
+                }
+                self.message = "There is no bet to call yet".to_owned();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    // Makes the move of the bot whose turn it is. A bot that cannot be made
+    // is reported and a human takes its seat.
+    pub fn play_bot(&mut self, mut state: GameState) -> GameState {
+        let player = state.players[state.current_turn].clone();
+        let name = match player.seat {
+            Seat::Bot(name) => name,
+            Seat::Human => return state,
+        };
+        let bot = match self.bots.entry(player.name) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                match BotKind::build_seat(&name, self.rng.next_u64()) {
+                    Ok(bot) => entry.insert(bot),
+                    Err(error) => {
+                        self.message = format!("{} cannot play: {}; seated a human instead",
+                                               state.player_name(player.name),
+                                               error);
+                        state.set_seat(player.name, Seat::Human);
+                        self.update(&state);
+                        return state;
+                    }
+                }
+            }
+        };
+        let gm = bot.choose(&state.view(state.current_turn), &state.rules);
+        self.play(state, gm)
+    }
+
+    // returns the rows of the screen besides the log and the hand
+    fn fixed_rows(&self, state: &GameState) -> usize {
+        // title, four pane headers, a line per player, the bet, picker and
+        // message
+        8 + state.players.len()
+    }
+
+    // returns the rows left for the log once the other panes are laid out
+    fn log_rows(&self, state: &GameState) -> usize {
+        self.height.saturating_sub(self.fixed_rows(state) + self.hand_rows(state).len()).max(1)
+    }
+
+    // returns the screen as exactly height lines of exactly width columns
+    pub fn render(&self, state: &GameState) -> Vec<String> {
+        let mut lines = vec![];
+        let turn = state.player_name(state.players[state.current_turn].name);
+        lines.push(format!(" BullPoker | {}'s turn | PgUp/PgDn: scroll log", turn));
+
+        lines.push(header("Players", self.width));
+        for (i, player) in state.players.iter().enumerate() {
+            let marker = if i == state.current_turn { ">" } else { " " };
+            let seat = match player.seat {
+                Seat::Human if player.name == self.viewer => " (you)".to_owned(),
+                Seat::Human => String::new(),
+                Seat::Bot(ref name) => format!(" ({})", name),
+            };
+            let cards = player.hand.cards.len();
+            lines.push(format!("{} {}{}: {} card{}",
+                               marker,
+                               state.player_name(player.name),
+                               seat,
+                               cards,
+                               if cards == 1 { "" } else { "s" }));
+        }
+
+        let rows = self.log_rows(state);
+        lines.push(header("Log", self.width));
+        let end = self.log.len() - self.scroll.min(self.log.len().saturating_sub(rows));
+        let start = end.saturating_sub(rows);
+        for i in start..start + rows {
+            lines.push(self.log.get(i).cloned().unwrap_or_default());
+        }
+
+        lines.push(header(&format!("Your hand ({})", state.player_name(self.viewer)),
+                          self.width));
+        lines.extend(self.hand_rows(state));
+        lines.push(header("Bet", self.width));
+        lines.push(match state.current_bet {
+            Some(bet) => format!("Current bet: {}", bet.to_text()),
+            None => "No bet yet".to_owned(),
+        });
+        if state.winner().is_some() || self.bot_to_move(state) {
+            lines.push(String::new());
+        } else {
+            lines.push(format!("Your bet: < {} >   Up/Down: bet  Left/Right: hand type  \
+                                Enter: bet  c: call  q: quit",
+                               self.ladder[self.pick].to_text()));
+        }
+        lines.push(self.message.clone());
+
+        lines.truncate(self.height);
+        while lines.len() < self.height {
+            lines.push(String::new());
+        }
+        lines.iter().map(|line| term::fit(line, self.width)).collect()
+    }
+
+    // returns the viewer's hand, boxed if the screen is tall enough to keep
+    // a few rows of log and on one line otherwise
+    fn hand_rows(&self, state: &GameState) -> Vec<String> {
+        let hand = match state.players.iter().find(|p| p.name == self.viewer) {
+            Some(player) => &player.hand,
+            None => return vec!["You are out of the game".to_owned()],
+        };
+        let boxed = RenderOptions {
+            width: Some(self.width),
+            ..self.cards
+        };
+        let rows: Vec<String> = hand.render(&boxed).lines().map(|s| s.to_owned()).collect();
+        if boxed.compact || self.fixed_rows(state) + rows.len() + 3 <= self.height {
+            rows
+        } else {
+            let compact = RenderOptions { compact: true, ..boxed };
+            hand.render(&compact).lines().map(|s| s.to_owned()).collect()
+        }
+    }
+}
+
+// Plays the game full screen until the player quits. Needs a terminal. Bots
+// move on their own, a little apart so that their moves can be followed.
+pub fn run(state: GameState, cards: RenderOptions) -> io::Result<()> {
+    let raw = match term::RawMode::enter() {
+        Some(raw) => raw,
+        None => return Err(io::Error::other("the full-screen UI needs a terminal")),
+    };
+    let (width, height) = term::size().unwrap_or((term::DEFAULT_WIDTH, 24));
+    let mut tui = Tui::new(&state, width, height, GameRng::random_seed());
+    tui.cards = cards;
+    let mut out = io::stdout();
+    // switch to the alternate screen and hide the cursor
+    write!(out, "\x1b[?1049h\x1b[?25l")?;
+    let result = tui.run_loop(state, &mut out);
+    write!(out, "\x1b[?25h\x1b[?1049l")?;
+    out.flush()?;
+    drop(raw);
+    result
+}
+
+impl Tui {
+    fn run_loop<W: Write>(&mut self, mut state: GameState, out: &mut W) -> io::Result<()> {
+        // a thread reads the keys so that the loop can notice resizes and
+        // let bots move while no key is pressed
+        let (sender, keys) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0; 64];
+            let stdin = io::stdin();
+            let mut stdin = stdin.lock();
+            loop {
+                match stdin.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if sender.send(buf[..n].to_vec()).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        let mut drawn = vec![];
+        let mut checked = Instant::now();
+        loop {
+            if checked.elapsed() >= RESIZE_POLL {
+                checked = Instant::now();
+                if let Some((width, height)) = term::size() {
+                    if (width, height) != (self.width, self.height) {
+                        self.width = width;
+                        self.height = height;
+                        write!(out, "\x1b[2J")?;
+                        drawn.clear();
+                    }
+                }
+            }
+            let screen = self.render(&state);
+            if screen != drawn {
+                draw(out, &screen)?;
+                drawn = screen;
+            }
+            let bot = self.bot_to_move(&state);
+            match keys.recv_timeout(if bot { BOT_DELAY } else { RESIZE_POLL }) {
+                Ok(bytes) => {
+                    for key in Key::parse(&bytes) {
+                        match self.press(key, &state) {
+                            Some(Action::Quit) => return Ok(()),
+                            Some(Action::Move(gm)) => state = self.play(state, gm),
+                            None => {}
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if bot {
+                        state = self.play_bot(state);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+    }
+}
+
+// Plays the keys against the game without a terminal, bots moving as soon as
+// it is their turn, and returns the game and the last screen
+pub fn headless(state: GameState,
+                keys: &[Key],
+                width: usize,
+                height: usize,
+                seed: u64)
+                -> (GameState, Vec<String>) {
+    let mut tui = Tui::new(&state, width, height, seed);
+    let mut state = state;
+    for &key in keys {
+        while tui.bot_to_move(&state) {
+            state = tui.play_bot(state);
+        }
+        match tui.press(key, &state) {
+            Some(Action::Quit) => break,
+            Some(Action::Move(gm)) => state = tui.play(state, gm),
+            None => {}
+        }
+    }
+    while tui.bot_to_move(&state) {
+        state = tui.play_bot(state);
+    }
+    let screen = tui.render(&state);
+    (state, screen)
+}
+
+// moves to the top left corner and overwrites the screen with the lines
+fn draw<W: Write>(out: &mut W, screen: &[String]) -> io::Result<()> {
+    write!(out, "\x1b[H{}", screen.join("\r\n"))?;
+    out.flush()
+}
+
+// returns a pane title drawn as a line across the screen
+fn header(title: &str, width: usize) -> String {
+    let mut line = format!("-- {} ", title);
+    while line.chars().count() < width {
+        line.push('-');
+    }
+    line
+}
+
+fn same_kind(a: &HandValue, b: &HandValue) -> bool {
+    mem::discriminant(a) == mem::discriminant(b)
+}
+
+#[cfg(test)]
+mod test {
+    use game::{GameMove, GameState, Rules, Seat};
+    use poker::HandValue;
+    use strategy::BotKind;
+    use term;
+    use tui::{headless, Key, Tui};
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(Key::parse(b"\x1b[A\x1bOB\x1b[5~c\r\x1b[1;5C\x1b"),
+                   vec![Key::Up, Key::Down, Key::PageUp, Key::Char('c'), Key::Enter, Key::Other,
+                        Key::Escape]);
+    }
+
+    #[test]
+    fn test_headless_game_against_bot() {
+        let mut state = GameState::seeded(2, 3, Rules::default(), 5).unwrap();
+        state.names = vec!["ann".to_owned()];
+        state.set_seat(1, BotKind::Caller.seat());
+        // two bets up from the lowest, then right to the lowest pair
+        let keys = [Key::Up, Key::Up, Key::Down, Key::Enter, Key::Right, Key::Enter];
+        let (state, screen) = headless(state, &keys, 60, 40, 1);
+        assert_eq!(screen.len(), 40);
+        assert!(screen.iter().all(|line| term::visible_len(line) == 60));
+        // the bot calls the first bet, so the second bet is in a new round
+        let text = screen.join("\n");
+        assert!(text.contains("ann bets high 3"), "{}", text);
+        assert!(text.contains("Player 1 called ann's high 3"), "{}", text);
+        assert!(text.contains("Player 1 (call): "), "{}", text);
+        assert!(text.contains("+-----+"), "{}", text);
+        assert!(state.history.len() >= 4);
+
+        // a short screen shows the hand on one line
+        let state = GameState::seeded(3, 3, Rules::default(), 5).unwrap();
+        let (_, screen) = headless(state, &[Key::Up], 40, 12, 1);
+        assert_eq!(screen.len(), 12);
+        assert!(!screen.join("\n").contains("+-----+"));
+        assert!(screen.iter().any(|line| line.contains(&HandValue::ladder()[1].to_text())));
+    }
+
+    #[test]
+    fn test_enter_refuses_to_repeat_the_top_bet() {
+        let state = GameState::seeded(2, 3, Rules::default(), 5).unwrap();
+        let top = *HandValue::ladder().last().unwrap();
+        let state = state.handle_gamemove(GameMove::Bet(top));
+        let mut tui = Tui::new(&state, 60, 40, 1);
+        assert!(tui.press(Key::Enter, &state).is_none());
+        assert!(tui.message.starts_with("No bet is higher"), "{}", tui.message);
+        // the message has the last row of the screen
+        let screen = tui.render(&state);
+        assert!(screen[39].starts_with("No bet is higher"), "{:?}", screen);
+        assert!(tui.press(Key::Char('c'), &state).is_some());
+    }
+
+    #[test]
+    fn test_seat_that_cannot_be_made_goes_to_a_human() {
+        let mut state = GameState::seeded(2, 3, Rules::default(), 5).unwrap();
+        state.set_seat(0, Seat::Bot("nn:no-such-weights.txt".to_owned()));
+        let (state, screen) = headless(state, &[], 80, 40, 1);
+        assert_eq!(state.players[0].seat, Seat::Human);
+        let text = screen.join("\n");
+        assert!(text.contains("Player 0 cannot play: "), "{}", text);
+        assert!(text.contains("Your hand (Player 0)"), "{}", text);
+    }
+}
@@ -0,0 +1,253 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use rand::Rng;
+
+use arena;
+use game::Rules;
+use rng::GameRng;
+use strategy::BotKind;
+use strategy::bluff::BluffConfig;
+use strategy::threshold::ThresholdBot;
+
+// A parameterised bot whose parameters can be tuned
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Family {
+    // call_below, raise_above
+    Threshold,
+    // aggression, bluff, call_below, raise_above, trust
+    Bluff,
+}
+
+impl FromStr for Family {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Family, String> {
+        match s {
+            "threshold" => Ok(Family::Threshold),
+            "bluff" => Ok(Family::Bluff),
+            _ => Err(format!("unknown bot family: {} (threshold or bluff)", s)),
+        }
+    }
+}
+
+impl Family {
+    // returns the (lowest, highest) value of every parameter
+    pub fn bounds(self) -> Vec<(f64, f64)> {
+        match self {
+            Family::Threshold => vec![(0.0, 1.0), (0.0, 1.0)],
+            Family::Bluff => vec![(0.0, 1.0), (0.0, 1.0), (0.0, 1.0), (0.0, 1.0), (0.0, 5.0)],
+        }
+    }
+
+    // returns the bot with the input parameters
+    pub fn bot(self, params: &[f64]) -> BotKind {
+        match self {
+            Family::Threshold => BotKind::Threshold(ThresholdBot::new(params[0], params[1])),
+            Family::Bluff => {
+                BotKind::Bluff(BluffConfig {
+                    aggression: params[0],
+                    bluff: params[1],
+                    call_below: params[2],
+                    raise_above: params[3],
+                    trust: params[4],
+                })
+            }
+        }
+    }
+}
+
+// How to search for good parameters
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuneConfig {
+    pub family: Family,
+    // opponents every candidate is scored against
+    pub panel: Vec<BotKind>,
+    pub population: usize,
+    pub generations: usize,
+    // games against each opponent, half of them in each seat
+    pub games: usize,
+    pub cards: u8,
+    pub rules: Rules,
+    // chance that each parameter of a child is mutated
+    pub mutation_rate: f64,
+    // standard deviation of a mutation as a fraction of the parameter range
+    pub mutation_scale: f64,
+    // best candidates copied unchanged into the next generation
+    pub elite: usize,
+    pub seed: u64,
+}
+
+impl Default for TuneConfig {
+    fn default() -> TuneConfig {
+        TuneConfig {
+            family: Family::Threshold,
+            panel: vec!["medium".parse().unwrap(), "bluff".parse().unwrap()],
+            population: 20,
+            generations: 20,
+            games: 40,
+            cards: 3,
+            rules: Rules::default(),
+            mutation_rate: 0.3,
+            mutation_scale: 0.1,
+            elite: 2,
+            seed: 0,
+        }
+    }
+}
+
+// A parameter vector and the share of its games it won
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub params: Vec<f64>,
+    pub fitness: f64,
+}
+
+// Returns the share of heads-up games the bot wins against the panel. The
+// games are dealt from the same seeds for every bot, so differences in
+// fitness come from the bots and not from the cards. Fails if the hands
+// cannot be dealt under the rules.
+pub fn fitness(config: &TuneConfig, bot: &BotKind) -> Result<f64, String> {
+    let mut wins = 0;
+    let mut played = 0;
+    for (o, opponent) in config.panel.iter().enumerate() {
+        for game in 0..config.games {
+            let seed = config.seed.wrapping_add((o * config.games + game) as u64);
+            let seat = game % 2;
+            let kinds = if seat == 0 {
+                [bot.clone(), opponent.clone()]
+            } else {
+                [opponent.clone(), bot.clone()]
+            };
+            let (places, _) = arena::play_game(&kinds, config.cards, config.rules, seed)?;
+            if places[seat] == 1.0 {
+                wins += 1;
+            }
+            played += 1;
+        }
+    }
+    Ok(wins as f64 / played.max(1) as f64)
+}
+
+// Evolves the population for the configured number of generations with
+// tournament selection, uniform crossover, gaussian mutation and elitism.
+// Calls `progress` with the best candidate of every generation and returns
+// the best candidate found.
+pub fn evolve(config: &TuneConfig, progress: &mut dyn FnMut(usize, &Candidate))
+              -> Result<Candidate, String> {
+    let bounds = config.family.bounds();
+    let mut rng = GameRng::from_seed(config.seed);
+    let mut population = (0..config.population.max(2))
+        .map(|_| {
+            let params = bounds.iter().map(|&(low, high)| rng.gen_range(low, high)).collect();
+            score(config, params)
+        })
+        .collect::<Result<Vec<Candidate>, String>>()?;
+    for generation in 0..config.generations {
+        population.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
+        progress(generation, &population[0]);
+        if generation + 1 == config.generations {
+            break;
+        }
+        let mut next: Vec<Candidate> = population.iter().take(config.elite).cloned().collect();
+        while next.len() < population.len() {
+            let first = select(&population, &mut rng);
+            let second = select(&population, &mut rng);
+            let params = bounds.iter()
+                .enumerate()
+                .map(|(i, &(low, high))| {
+                    let mut value = if rng.gen() { first.params[i] } else { second.params[i] };
+                    if rng.next_f64() < config.mutation_rate {
+                        value += gaussian(&mut rng) * config.mutation_scale * (high - low);
+                    }
+                    value.max(low).min(high)
+                })
+                .collect();
+            next.push(score(config, params)?);
+        }
+        population = next;
+    }
+    population.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
+    Ok(population.swap_remove(0))
+}
+
+// Writes the best bot as a line the seat command and the arena accept,
+// followed by a comment with how it was found
+pub fn write_best<W: Write>(config: &TuneConfig, best: &Candidate, out: &mut W) -> io::Result<()> {
+    let panel: Vec<String> = config.panel.iter().map(|k| k.to_string()).collect();
+    writeln!(out, "{}", config.family.bot(&best.params))?;
+    writeln!(out,
+             "# won {:.1}% of {} games against {} after {} generations (seed {})",
+             100.0 * best.fitness,
+             config.games * config.panel.len(),
+             panel.join(","),
+             config.generations,
+             config.seed)
+}
+
+pub fn save_best(config: &TuneConfig, best: &Candidate, path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_best(config, best, &mut out)?;
+    out.flush()
+}
+
+fn score(config: &TuneConfig, params: Vec<f64>) -> Result<Candidate, String> {
+    let fitness = fitness(config, &config.family.bot(&params))?;
+    Ok(Candidate { params, fitness })
+}
+
+// the fitter of three random candidates
+fn select<'a>(population: &'a [Candidate], rng: &mut GameRng) -> &'a Candidate {
+    let mut best = rng.choose(population).unwrap();
+    for _ in 0..2 {
+        let other = rng.choose(population).unwrap();
+        if other.fitness > best.fitness {
+            best = other;
+        }
+    }
+    best
+}
+
+// standard normal sample by the Box-Muller transform
+fn gaussian(rng: &mut GameRng) -> f64 {
+    let u = 1.0 - rng.next_f64();
+    let v = rng.next_f64();
+    (-2.0 * u.ln()).sqrt() * (2.0 * ::std::f64::consts::PI * v).cos()
+}
+
+#[cfg(test)]
+mod test {
+    use strategy::BotKind;
+    use tune::{evolve, write_best, Family, TuneConfig};
+
+    #[test]
+    fn test_evolve_keeps_the_best() {
+        let config = TuneConfig {
+            family: Family::Bluff,
+            panel: vec![BotKind::Random],
+            population: 6,
+            generations: 4,
+            games: 6,
+            cards: 2,
+            ..TuneConfig::default()
+        };
+        let mut bests = vec![];
+        let best = evolve(&config, &mut |_, c| bests.push(c.fitness)).unwrap();
+        assert_eq!(bests.len(), 4);
+        // elitism and fixed seeds mean the best never gets worse
+        assert!(bests.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(best.fitness, bests[3]);
+        for (&value, &(low, high)) in best.params.iter().zip(&Family::Bluff.bounds()) {
+            assert!(value >= low && value <= high);
+        }
+        assert_eq!(evolve(&config, &mut |_, _| {}), Ok(best.clone()));
+
+        let mut out = vec![];
+        write_best(&config, &best, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let kind: BotKind = text.lines().next().unwrap().parse().unwrap();
+        assert_eq!(kind, Family::Bluff.bot(&best.params));
+    }
+}
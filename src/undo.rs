@@ -0,0 +1,119 @@
+use std::mem;
+
+use game::{GameMove, GameState, UndoPolicy};
+
+// The games before and after the current one, so that moves can be taken
+// back and made again. Every entry is a whole GameState, RNG included, so a
+// move made again deals exactly the same cards as the first time.
+#[derive(Default)]
+pub struct UndoStack {
+    undo: Vec<GameState>,
+    redo: Vec<GameState>,
+}
+
+impl UndoStack {
+    pub fn new() -> UndoStack {
+        UndoStack::default()
+    }
+
+    // makes the move, remembering the game before it as the rules allow.
+    // A new move cannot be redone over, so it forgets the undone moves.
+    pub fn play(&mut self, state: &mut GameState, gm: GameMove) {
+        self.redo.clear();
+        match state.rules.undo {
+            UndoPolicy::Always => self.undo.push(state.clone()),
+            UndoPolicy::LastMove => self.undo = vec![state.clone()],
+            UndoPolicy::Never => {}
+        }
+        *state = state.clone().handle_gamemove(gm);
+    }
+
+    // takes back the last move, or explains why it cannot be
+    pub fn undo(&mut self, state: &mut GameState) -> Result<(), String> {
+        let previous = match (self.undo.pop(), state.rules.undo) {
+            (_, UndoPolicy::Never) => return Err("The rules do not allow undoing moves".to_owned()),
+            (Some(previous), _) => previous,
+            (None, UndoPolicy::LastMove) => {
+                return Err("Only the last move can be undone, before the next player acts"
+                    .to_owned())
+            }
+            (None, UndoPolicy::Always) => return Err("There is no move to undo".to_owned()),
+        };
+        self.redo.push(mem::replace(state, previous));
+        Ok(())
+    }
+
+    // makes the last undone move again
+    pub fn redo(&mut self, state: &mut GameState) -> Result<(), String> {
+        let next = match self.redo.pop() {
+            Some(next) => next,
+            None => return Err("There is no move to redo".to_owned()),
+        };
+        self.undo.push(mem::replace(state, next));
+        Ok(())
+    }
+
+    // forgets every move, e.g. when another game is loaded
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use game::{GameMove, GameState, Rules, UndoPolicy};
+    use poker::HandValue;
+    use undo::UndoStack;
+
+    fn bet(text: &str) -> GameMove {
+        GameMove::Bet(HandValue::from_text(text).unwrap())
+    }
+
+    #[test]
+    fn test_undo_restores_deals() {
+        let mut state = GameState::seeded(2, 3, Rules::default(), 11).unwrap();
+        let mut stack = UndoStack::new();
+        stack.play(&mut state, bet("high 5"));
+        stack.play(&mut state, GameMove::Call());
+        let called = state.clone();
+        assert!(stack.undo(&mut state).is_ok());
+        assert_eq!(state.current_bet, Some(HandValue::from_text("high 5").unwrap()));
+        // calling again deals the same next round
+        let again = state.clone().handle_gamemove(GameMove::Call());
+        assert!(stack.redo(&mut state).is_ok());
+        for game in &[&state, &again] {
+            assert_eq!(game.history.len(), called.history.len());
+            for (a, b) in game.players.iter().zip(&called.players) {
+                assert_eq!(a.hand.cards, b.hand.cards);
+            }
+        }
+        assert!(stack.redo(&mut state).is_err());
+        assert!(stack.undo(&mut state).is_ok());
+        assert!(stack.undo(&mut state).is_ok());
+        assert!(state.history.len() == 1 && state.current_bet.is_none());
+        assert!(stack.undo(&mut state).is_err());
+        // a new move forgets the undone ones
+        stack.play(&mut state, bet("pair 2"));
+        assert!(stack.redo(&mut state).is_err());
+    }
+
+    #[test]
+    fn test_undo_policies() {
+        let rules = Rules { undo: UndoPolicy::LastMove, ..Rules::default() };
+        let mut state = GameState::seeded(3, 3, rules, 1).unwrap();
+        let mut stack = UndoStack::new();
+        stack.play(&mut state, bet("high 5"));
+        stack.play(&mut state, bet("high 6"));
+        assert!(stack.undo(&mut state).is_ok());
+        assert!(stack.undo(&mut state).is_err());
+        assert!(stack.redo(&mut state).is_ok());
+        assert_eq!(state.current_bet, Some(HandValue::from_text("high 6").unwrap()));
+
+        let rules = Rules { undo: UndoPolicy::Never, ..Rules::default() };
+        let mut state = GameState::seeded(3, 3, rules, 1).unwrap();
+        stack.clear();
+        stack.play(&mut state, bet("high 5"));
+        assert!(stack.undo(&mut state).is_err());
+    }
+}